@@ -0,0 +1,257 @@
+//! Cache of cloned remote repositories
+//!
+//! Each clone lives in `<temp dir>/clones/<repo name>-<hash>`, keyed by a hash of the
+//! normalized URL, so two repositories that share a name never share a directory.
+//! A lock file guards each clone against concurrent runs, and a small `.url` file
+//! next to it records where it came from and when it was last used.
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+
+/// A cache directory holding one clone per remote URL
+pub struct CloneCache {
+    root: PathBuf,
+}
+
+/// A cached clone, as shown by `git2pdf cache list`
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    /// Directory name, e.g. "core-3f2a9c0d1b7e4a55"
+    pub key: String,
+    /// Location of the clone
+    pub path: PathBuf,
+    /// URL the clone was made from (None for incomplete clones)
+    pub url: Option<String>,
+    /// Last time the clone was used
+    pub last_used: Option<SystemTime>,
+    /// Size on disk in bytes
+    pub size: u64,
+}
+
+/// Exclusive lock on a cached clone, released when dropped
+pub struct CacheLock {
+    _file: File,
+}
+
+impl CloneCache {
+    pub fn new(temp_dir: &Path) -> Self {
+        Self { root: temp_dir.join("clones") }
+    }
+
+    /// Directory of the clone of `url`
+    pub fn path_for(&self, url: &str, repo_name: &str) -> PathBuf {
+        self.root.join(cache_key(url, repo_name))
+    }
+
+    /// Lock the clone at `path`, waiting for other runs using it to finish
+    pub fn lock(&self, path: &Path) -> Result<CacheLock> {
+        fs::create_dir_all(&self.root)?;
+        let file = File::create(lock_path(path))
+            .context("Failed to create cache lock file")?;
+        if file.try_lock().is_err() {
+            eprintln!("Waiting for another git2pdf run using {}...", path.display());
+            file.lock().context("Failed to lock cached clone")?;
+        }
+        Ok(CacheLock { _file: file })
+    }
+
+    /// Whether the clone at `path` was completed (an interrupted clone is not)
+    pub fn is_complete(&self, path: &Path) -> bool {
+        path.exists() && url_path(path).exists()
+    }
+
+    /// Record a completed clone of `url` and mark it as used now
+    pub fn mark_used(&self, path: &Path, url: &str) -> Result<()> {
+        fs::write(url_path(path), url)
+            .context("Failed to write cache metadata")
+    }
+
+    /// Remove the clone at `path` (the caller holds its lock)
+    pub fn remove(&self, path: &Path) -> Result<()> {
+        let _ = fs::remove_file(url_path(path));
+        if path.exists() {
+            fs::remove_dir_all(path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// All cached clones, most recently used first
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+        if !self.root.exists() {
+            return Ok(entries);
+        }
+        for dir_entry in fs::read_dir(&self.root)? {
+            let dir_entry = dir_entry?;
+            if !dir_entry.file_type()?.is_dir() {
+                continue;
+            }
+            let path = dir_entry.path();
+            let url_file = url_path(&path);
+            entries.push(CacheEntry {
+                key: dir_entry.file_name().to_string_lossy().to_string(),
+                url: fs::read_to_string(&url_file).ok(),
+                last_used: fs::metadata(&url_file).and_then(|m| m.modified()).ok(),
+                size: dir_size(&path),
+                path,
+            });
+        }
+        entries.sort_by_key(|e| std::cmp::Reverse(e.last_used));
+        Ok(entries)
+    }
+
+    /// Remove clones not used within `max_age` (all clones if None), skipping clones
+    /// in use by another run. Returns the removed entries.
+    pub fn prune(&self, max_age: Option<Duration>) -> Result<Vec<CacheEntry>> {
+        let now = SystemTime::now();
+        let mut removed = Vec::new();
+        for entry in self.entries()? {
+            let expired = match (max_age, entry.last_used) {
+                (None, _) | (_, None) => true,
+                (Some(max_age), Some(last_used)) => {
+                    now.duration_since(last_used).unwrap_or_default() > max_age
+                }
+            };
+            if !expired {
+                continue;
+            }
+            let lock_file = File::create(lock_path(&entry.path))?;
+            if lock_file.try_lock().is_err() {
+                continue;
+            }
+            // The lock file stays: a run waiting for it holds the same file, and would
+            // share the clone with a run locking a newly created one
+            self.remove(&entry.path)?;
+            drop(lock_file);
+            removed.push(entry);
+        }
+        Ok(removed)
+    }
+}
+
+/// Cache key for a URL: the repository name plus a hash of the normalized URL.
+///
+/// Local repositories are keyed by their absolute path, so `../repo` and `/abs/repo`
+/// share a clone, and `../repo` typed in different directories does not.
+pub fn cache_key(url: &str, repo_name: &str) -> String {
+    let url = match local_path(url).and_then(|path| fs::canonicalize(path).ok()) {
+        Some(path) => path.to_string_lossy().to_string(),
+        None => url.to_string(),
+    };
+    format!("{}-{:016x}", repo_name, fnv1a(normalize_url(&url).as_bytes()))
+}
+
+/// The path of a local repository URL: `file://` URLs and plain paths, but not
+/// scp-like `host:path` URLs (a colon before the first slash)
+fn local_path(url: &str) -> Option<&str> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return Some(path);
+    }
+    let scp_like = url.find(':').is_some_and(|colon| !url[..colon].contains('/'));
+    (!url.contains("://") && !scp_like).then_some(url)
+}
+
+/// Normalize a git URL so that equivalent spellings share a clone:
+/// scheme, user, trailing slashes and ".git" are dropped, scp-like `git@host:path`
+/// becomes `host/path`, and the host is lowercased.
+///
+/// `https://github.com/user/repo.git` and `git@github.com:user/repo` both become
+/// `github.com/user/repo`.
+pub fn normalize_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/').trim_end_matches(".git");
+    let (rest, scp_like) = match url.split_once("://") {
+        Some((_, rest)) => (rest, false),
+        None => (url, true),
+    };
+    let rest = match rest.split_once('@') {
+        Some((user, host_and_path)) if !user.contains('/') => host_and_path,
+        _ => rest,
+    };
+    let (host, path) = if scp_like {
+        rest.split_once(':').unwrap_or(("", rest))
+    } else {
+        rest.split_once('/').unwrap_or((rest, ""))
+    };
+    let path = path.trim_start_matches('/');
+    if host.is_empty() {
+        format!("/{}", path)
+    } else {
+        format!("{}/{}", host.to_lowercase(), path)
+    }
+}
+
+/// FNV-1a, a hash that is stable across Rust versions (unlike `DefaultHasher`)
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn lock_path(path: &Path) -> PathBuf {
+    sibling(path, ".lock")
+}
+
+fn url_path(path: &Path) -> PathBuf {
+    sibling(path, ".url")
+}
+
+/// `path` with `suffix` appended (not `with_extension`, repo names may contain dots)
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Total size of the files below `path`
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| match e.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&e.path()),
+            Ok(_) => e.metadata().map(|m| m.len()).unwrap_or(0),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(normalize_url("https://github.com/user/repo.git"), "github.com/user/repo");
+        assert_eq!(normalize_url("git@GitHub.com:user/repo"), "github.com/user/repo");
+        assert_eq!(normalize_url("ssh://git@github.com/user/repo/"), "github.com/user/repo");
+        assert_eq!(normalize_url("file:///srv/mirrors/repo.git"), "/srv/mirrors/repo");
+    }
+
+    #[test]
+    fn test_cache_key_distinguishes_same_name() {
+        let a = cache_key("https://github.com/a/core", "core");
+        let b = cache_key("https://github.com/b/core", "core");
+        assert_ne!(a, b);
+        assert!(a.starts_with("core-"));
+        assert_eq!(a, cache_key("git@github.com:a/core.git", "core"));
+    }
+
+    #[test]
+    fn test_cache_key_canonicalizes_local_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        fs::create_dir_all(dir.path().join("sub")).unwrap();
+        fs::create_dir_all(&repo).unwrap();
+        let key = cache_key(&repo.to_string_lossy(), "repo");
+        assert_eq!(key, cache_key(&dir.path().join("sub/../repo").to_string_lossy(), "repo"));
+        assert_eq!(key, cache_key(&format!("file://{}", repo.display()), "repo"));
+        assert_eq!(local_path("git@github.com:a/core"), None);
+        assert_eq!(local_path("../repo"), Some("../repo"));
+    }
+}
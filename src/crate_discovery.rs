@@ -1,9 +1,12 @@
 //! Rust crate discovery in a repository
 
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
 use serde::Deserialize;
 
 /// Information about a discovered Rust crate
@@ -15,10 +18,114 @@ pub struct CrateInfo {
     pub path: PathBuf,
     /// Whether this is a workspace member
     pub is_workspace_member: bool,
+    /// Why the crate was included
+    pub inclusion: Inclusion,
+    /// Whether cargo builds this crate by default (`workspace.default-members`)
+    pub is_default_member: bool,
     /// Crate version
     pub version: String,
     /// Crate description
     pub description: Option<String>,
+    /// License (SPDX expression)
+    pub license: Option<String>,
+    /// Crate authors
+    pub authors: Vec<String>,
+    /// Repository URL
+    pub repository: Option<String>,
+    /// Rust edition (2015 if the manifest does not set one, like cargo)
+    pub edition: String,
+    /// Feature names (only known with the `cargo metadata` backend)
+    pub features: Vec<String>,
+    /// Build targets, from the manifest or `cargo metadata` (files outside of all targets
+    /// are classified by path)
+    pub targets: Vec<CrateTarget>,
+    /// Directories of the crates this crate depends on with `path` dependencies
+    /// (`[dependencies]` only, not dev- or build-dependencies)
+    pub path_dependencies: Vec<PathBuf>,
+}
+
+/// Why a crate was included in the printout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inclusion {
+    /// The package in the root Cargo.toml
+    RootPackage,
+    /// The root package of a workspace below the repository root
+    WorkspaceRoot { workspace: String },
+    /// A member of a workspace (`workspace` is "" for the repository root), listed by
+    /// the `members` entry `pattern`
+    Member { workspace: String, pattern: String },
+    /// A crate outside of any workspace, found by the recursive search
+    Found,
+    /// A package reported by `cargo metadata`
+    CargoMetadata,
+}
+
+impl std::fmt::Display for Inclusion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Inclusion::RootPackage => write!(f, "package in the root Cargo.toml"),
+            Inclusion::WorkspaceRoot { workspace } => write!(f, "root package of the workspace in {}", workspace),
+            Inclusion::Member { workspace, pattern } if workspace.is_empty() => {
+                write!(f, "workspace member (\"{}\")", pattern)
+            }
+            Inclusion::Member { workspace, pattern } => {
+                write!(f, "member of the workspace in {} (\"{}\")", workspace, pattern)
+            }
+            Inclusion::Found => write!(f, "found outside of any workspace"),
+            Inclusion::CargoMetadata => write!(f, "reported by cargo metadata"),
+        }
+    }
+}
+
+/// Directories skipped by the recursive search by default: test fixtures and templates
+/// are usually not meant to be printed
+pub const DEFAULT_SKIP_PATTERNS: &str = "**/tests,**/fixtures,**/test-fixtures,**/testdata,**/test-data,**/templates";
+
+/// Kind of a build target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetKind {
+    Lib,
+    Bin,
+    Example,
+    Test,
+    Bench,
+    BuildScript,
+}
+
+/// A build target of a crate (`[lib]`, `[[bin]]`, `[[example]]`, `[[test]]`, `[[bench]]`
+/// or the build script)
+#[derive(Debug, Clone)]
+pub struct CrateTarget {
+    pub kind: TargetKind,
+    pub name: String,
+    /// Absolute path to the target's root source file
+    pub path: PathBuf,
+}
+
+/// Kind of manifest at the repository root
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootManifest {
+    /// No Cargo.toml at the root, crates were searched recursively
+    None,
+    /// A single package
+    Package,
+    /// A workspace whose root is also a package
+    Workspace,
+    /// A workspace without a root package
+    VirtualWorkspace,
+}
+
+/// Result of crate discovery
+#[derive(Debug)]
+pub struct Discovery {
+    /// Discovered crates, sorted by name
+    pub crates: Vec<CrateInfo>,
+    /// Kind of the root manifest
+    pub root: RootManifest,
+    /// Problems with the workspace layout (members without a Cargo.toml, nested workspaces)
+    pub warnings: Vec<String>,
+    /// Crates left out, as (directory relative to the repository root, reason)
+    pub skipped: Vec<(String, String)>,
 }
 
 /// Minimal Cargo.toml structure for parsing
@@ -26,143 +133,689 @@ pub struct CrateInfo {
 struct CargoToml {
     package: Option<Package>,
     workspace: Option<Workspace>,
+    #[serde(flatten)]
+    targets: TargetTables,
+    #[serde(default)]
+    dependencies: BTreeMap<String, toml::Value>,
 }
 
 #[derive(Debug, Deserialize)]
 struct Package {
     name: String,
-    #[serde(default = "default_version")]
-    version: String,
-    description: Option<String>,
+    version: Option<Inheritable<String>>,
+    description: Option<Inheritable<String>>,
+    license: Option<Inheritable<String>>,
+    authors: Option<Inheritable<Vec<String>>>,
+    repository: Option<Inheritable<String>>,
+    edition: Option<Inheritable<String>>,
+    build: Option<BuildScript>,
+    autobins: Option<bool>,
+    autoexamples: Option<bool>,
+    autotests: Option<bool>,
+    autobenches: Option<bool>,
 }
 
-fn default_version() -> String {
-    "0.0.0".to_string()
+/// A package field that may be inherited from `[workspace.package]`
+/// (`version.workspace = true`)
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Inheritable<T> {
+    Value(T),
+    Workspace { workspace: bool },
 }
 
+/// Resolve an inheritable field against the workspace's `[workspace.package]` value
+fn inherit<T: Clone>(field: Option<Inheritable<T>>, inherited: Option<&T>) -> Option<T> {
+    match field? {
+        Inheritable::Value(value) => Some(value),
+        Inheritable::Workspace { workspace: true } => inherited.cloned(),
+        Inheritable::Workspace { workspace: false } => None,
+    }
+}
+
+/// `package.build`: a path, or false to disable the `build.rs` default
 #[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum BuildScript {
+    Enabled(bool),
+    Path(String),
+}
+
+/// The `[lib]`, `[[bin]]`, `[[example]]`, `[[test]]` and `[[bench]]` tables
+#[derive(Debug, Default, Deserialize)]
+struct TargetTables {
+    lib: Option<TargetTable>,
+    #[serde(default)]
+    bin: Vec<TargetTable>,
+    #[serde(default)]
+    example: Vec<TargetTable>,
+    #[serde(default)]
+    test: Vec<TargetTable>,
+    #[serde(default)]
+    bench: Vec<TargetTable>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TargetTable {
+    name: Option<String>,
+    path: Option<String>,
+}
+
+/// `[workspace.package]`: fields members can inherit
+#[derive(Debug, Default, Deserialize)]
+struct WorkspacePackage {
+    version: Option<String>,
+    description: Option<String>,
+    license: Option<String>,
+    authors: Option<Vec<String>>,
+    repository: Option<String>,
+    edition: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 struct Workspace {
     members: Option<Vec<String>>,
     #[serde(default)]
     exclude: Vec<String>,
+    default_members: Option<Vec<String>>,
+    package: Option<WorkspacePackage>,
+    #[serde(default)]
+    dependencies: BTreeMap<String, toml::Value>,
 }
 
-/// Discover all Rust crates in a repository
-pub fn discover_crates(repo_path: &Path) -> Result<Vec<CrateInfo>> {
-    let mut crates = Vec::new();
+/// Discover all Rust crates in a repository.
+///
+/// Without a root Cargo.toml the repository is searched recursively; crates below a
+/// directory matching one of `skip` (globs relative to the repository root, see
+/// [`DEFAULT_SKIP_PATTERNS`]) are left out.
+pub fn discover_crates(repo_path: &Path, skip: &[String]) -> Result<Discovery> {
+    let mut discovery = Discovery {
+        crates: Vec::new(),
+        root: RootManifest::None,
+        warnings: Vec::new(),
+        skipped: Vec::new(),
+    };
     
     // Check if there's a root Cargo.toml
     let root_cargo = repo_path.join("Cargo.toml");
     
     if !root_cargo.exists() {
         // No Cargo.toml at root, search recursively
-        return discover_crates_recursive(repo_path);
+        let skip = skip.iter()
+            .filter(|p| !p.trim().is_empty())
+            .map(|p| PathPattern::new(p.trim()))
+            .collect::<Result<Vec<_>>>()?;
+        discover_crates_recursive(repo_path, &skip, &mut discovery)?;
+        discovery.crates.sort_by(|a, b| a.name.cmp(&b.name));
+        discovery.skipped.sort();
+        return Ok(discovery);
     }
     
     let content = fs::read_to_string(&root_cargo)
         .context("Failed to read root Cargo.toml")?;
     
-    let cargo_toml: CargoToml = toml::from_str(&content)
+    let mut cargo_toml: CargoToml = toml::from_str(&content)
         .context("Failed to parse root Cargo.toml")?;
     
     // Check if it's a workspace
-    if let Some(workspace) = cargo_toml.workspace {
-        // It's a workspace - discover members
-        if let Some(members) = workspace.members {
-            for member_pattern in members {
-                let member_crates = expand_workspace_member(repo_path, &member_pattern, &workspace.exclude)?;
-                crates.extend(member_crates);
-            }
-        }
-        
-        // Also check if the root is a package
-        if let Some(package) = cargo_toml.package {
-            crates.push(CrateInfo {
-                name: package.name,
-                path: repo_path.to_path_buf(),
-                is_workspace_member: false,
-                version: package.version,
-                description: package.description,
-            });
-        }
-    } else if let Some(package) = cargo_toml.package {
+    if let Some(workspace) = cargo_toml.workspace.take() {
+        discovery.root = if cargo_toml.package.is_some() {
+            RootManifest::Workspace
+        } else {
+            RootManifest::VirtualWorkspace
+        };
+        discover_workspace(repo_path, repo_path, cargo_toml, &workspace, &mut discovery)?;
+    } else if cargo_toml.package.is_some() {
         // It's a single crate
-        crates.push(CrateInfo {
-            name: package.name,
-            path: repo_path.to_path_buf(),
-            is_workspace_member: false,
-            version: package.version,
-            description: package.description,
-        });
+        discovery.root = RootManifest::Package;
+        discovery.crates.extend(crate_info(cargo_toml, repo_path, Inclusion::RootPackage, None));
     }
     
     // Sort by name for consistent output
-    crates.sort_by(|a, b| a.name.cmp(&b.name));
-    
-    // Remove duplicates (by path)
-    crates.dedup_by(|a, b| a.path == b.path);
+    discovery.crates.sort_by(|a, b| a.name.cmp(&b.name));
     
-    Ok(crates)
+    Ok(discovery)
 }
 
-/// Expand a workspace member pattern (supports glob patterns like "crates/*")
-fn expand_workspace_member(
+/// Add the members (and the root package, if any) of the workspace whose root manifest
+/// `manifest` is in `root`
+fn discover_workspace(
     repo_path: &Path,
-    pattern: &str,
-    exclude: &[String],
-) -> Result<Vec<CrateInfo>> {
-    let mut crates = Vec::new();
-    
-    if pattern.contains('*') {
-        // It's a glob pattern
-        let base_path = repo_path.join(pattern.split('*').next().unwrap_or(""));
-        
-        if base_path.exists() && base_path.is_dir() {
-            for entry in fs::read_dir(&base_path)? {
-                let entry = entry?;
-                let path = entry.path();
-                
-                if path.is_dir() {
-                    // Check if excluded
-                    let rel_path = path.strip_prefix(repo_path)
-                        .map(|p| p.to_string_lossy().to_string())
-                        .unwrap_or_default();
-                    
-                    if exclude.iter().any(|e| rel_path.starts_with(e)) {
-                        continue;
-                    }
-                    
-                    if let Some(crate_info) = try_parse_crate(&path)? {
-                        crates.push(CrateInfo {
-                            is_workspace_member: true,
-                            ..crate_info
-                        });
+    root: &Path,
+    manifest: CargoToml,
+    workspace: &Workspace,
+    discovery: &mut Discovery,
+) -> Result<()> {
+    let first = discovery.crates.len();
+    let workspace_dir = relative_path(repo_path, root);
+    
+    let exclude = workspace.exclude.iter()
+        .map(|e| PathPattern::new(e))
+        .collect::<Result<Vec<_>>>()?;
+    
+    // Discover members, skipping directories already included (e.g. listed twice)
+    let mut seen: HashSet<PathBuf> = discovery.crates.iter().map(|c| normalize_path(&c.path)).collect();
+    for member_pattern in workspace.members.iter().flatten() {
+        for member_path in expand_workspace_member(root, member_pattern)? {
+            let rel_path = relative_path(root, &member_path);
+            if rel_path.is_empty() || exclude.iter().any(|e| e.contains(&rel_path)) {
+                continue;
+            }
+            if !seen.insert(normalize_path(&member_path)) {
+                continue;
+            }
+            let display_path = relative_path(repo_path, &member_path);
+            match try_parse_manifest(&member_path)? {
+                // Glob matches without a manifest are plain directories (e.g. "crates/README")
+                None if is_glob(member_pattern) => {}
+                None => discovery.warnings.push(format!(
+                    "Workspace member {} has no Cargo.toml", display_path
+                )),
+                Some(member) => {
+                    if member.workspace.is_some() {
+                        discovery.warnings.push(format!(
+                            "Workspace member {} is a nested workspace (it has its own [workspace] table)",
+                            display_path
+                        ));
                     }
+                    let inclusion = Inclusion::Member {
+                        workspace: workspace_dir.clone(),
+                        pattern: member_pattern.clone(),
+                    };
+                    discovery.crates.extend(crate_info(member, &member_path, inclusion, Some((root, workspace))));
                 }
             }
         }
+    }
+    
+    // Also check if the root is a package
+    let inclusion = if root == repo_path {
+        Inclusion::RootPackage
     } else {
-        // Exact path
-        let member_path = repo_path.join(pattern);
-        
-        // Check if excluded
-        if exclude.iter().any(|e| pattern.starts_with(e)) {
-            return Ok(crates);
+        Inclusion::WorkspaceRoot { workspace: workspace_dir }
+    };
+    let has_root_package = manifest.package.is_some();
+    discovery.crates.extend(crate_info(manifest, root, inclusion, Some((root, workspace))));
+    
+    // Without default-members, cargo builds the root package of a workspace, or all
+    // members of a virtual workspace
+    let crates = &mut discovery.crates[first..];
+    if let Some(default_members) = &workspace.default_members {
+        let patterns = default_members.iter()
+            .map(|m| PathPattern::new(m))
+            .collect::<Result<Vec<_>>>()?;
+        for c in crates {
+            let rel_path = relative_path(root, &c.path);
+            c.is_default_member = patterns.iter().any(|p| p.matches(&rel_path));
         }
-        
-        if let Some(crate_info) = try_parse_crate(&member_path)? {
-            crates.push(CrateInfo {
-                is_workspace_member: true,
-                ..crate_info
-            });
+    } else if has_root_package {
+        for c in crates {
+            c.is_default_member = !c.is_workspace_member;
+        }
+    }
+    
+    Ok(())
+}
+
+/// Output of `cargo metadata --format-version 1` (the parts used here)
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    packages: Vec<MetadataPackage>,
+    workspace_root: PathBuf,
+    /// Only reported by cargo 1.71 and later
+    workspace_default_members: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataPackage {
+    id: String,
+    name: String,
+    version: String,
+    description: Option<String>,
+    license: Option<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    repository: Option<String>,
+    manifest_path: PathBuf,
+    edition: String,
+    features: BTreeMap<String, Vec<String>>,
+    targets: Vec<MetadataTarget>,
+    dependencies: Vec<MetadataDependency>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataDependency {
+    /// Only set for path dependencies
+    path: Option<PathBuf>,
+    /// None for normal dependencies, "dev" or "build" otherwise
+    kind: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataTarget {
+    name: String,
+    kind: Vec<String>,
+    src_path: PathBuf,
+}
+
+/// Discover crates with `cargo metadata --no-deps`, which reports the exact targets,
+/// features and edition of each workspace member. Falls back to parsing the
+/// Cargo.toml files (with a warning) if there is no root manifest or cargo fails.
+pub fn discover_crates_with_metadata(repo_path: &Path, skip: &[String]) -> Result<Discovery> {
+    let root_cargo = repo_path.join("Cargo.toml");
+    let metadata = if root_cargo.exists() {
+        cargo_metadata(&root_cargo)
+    } else {
+        Err(anyhow!("no Cargo.toml at the repository root"))
+    };
+    let metadata = match metadata {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            let mut discovery = discover_crates(repo_path, skip)?;
+            discovery.warnings.push(format!(
+                "cargo metadata is not available ({:#}), parsed Cargo.toml files instead", e
+            ));
+            return Ok(discovery);
+        }
+    };
+    
+    // Report paths below `repo_path` even if cargo canonicalized them
+    let rebase = |path: &Path| match path.strip_prefix(&metadata.workspace_root) {
+        Ok(rel) if rel.as_os_str().is_empty() => repo_path.to_path_buf(),
+        Ok(rel) => repo_path.join(rel),
+        Err(_) => path.to_path_buf(),
+    };
+    
+    let mut discovery = Discovery {
+        crates: Vec::new(),
+        root: RootManifest::VirtualWorkspace,
+        warnings: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for package in &metadata.packages {
+        let dir = package.manifest_path.parent().unwrap_or(&metadata.workspace_root);
+        let is_root = dir == metadata.workspace_root;
+        if is_root {
+            discovery.root = if metadata.packages.len() == 1 {
+                RootManifest::Package
+            } else {
+                RootManifest::Workspace
+            };
+        }
+        discovery.crates.push(CrateInfo {
+            name: package.name.clone(),
+            path: rebase(dir),
+            is_workspace_member: !is_root,
+            inclusion: Inclusion::CargoMetadata,
+            is_default_member: metadata.workspace_default_members.as_ref()
+                .is_none_or(|ids| ids.contains(&package.id)),
+            version: package.version.clone(),
+            description: package.description.clone(),
+            license: package.license.clone(),
+            authors: package.authors.clone(),
+            repository: package.repository.clone(),
+            edition: package.edition.clone(),
+            features: package.features.keys().cloned().collect(),
+            targets: package.targets.iter()
+                .map(|t| CrateTarget {
+                    kind: target_kind(&t.kind),
+                    name: t.name.clone(),
+                    path: rebase(&t.src_path),
+                })
+                .collect(),
+            path_dependencies: package.dependencies.iter()
+                .filter(|d| d.kind.is_none())
+                .filter_map(|d| d.path.as_deref())
+                .map(|path| normalize_path(&rebase(path)))
+                .collect(),
+        });
+    }
+    
+    discovery.crates.sort_by(|a, b| a.name.cmp(&b.name));
+    
+    Ok(discovery)
+}
+
+/// Run `cargo metadata --no-deps` for a manifest
+fn cargo_metadata(manifest_path: &Path) -> Result<Metadata> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--no-deps", "--format-version", "1", "--manifest-path"])
+        .arg(manifest_path)
+        .output()
+        .context("Failed to run cargo")?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.lines()
+            .map(|l| l.trim())
+            .find(|l| !l.is_empty())
+            .unwrap_or("cargo metadata failed");
+        bail!("{}", message);
+    }
+    
+    serde_json::from_slice(&output.stdout)
+        .context("Failed to parse cargo metadata output")
+}
+
+/// Map cargo's target kinds ("lib", "rlib", "proc-macro", "bin", "custom-build", ...)
+fn target_kind(kinds: &[String]) -> TargetKind {
+    match kinds.first().map(|k| k.as_str()) {
+        Some("bin") => TargetKind::Bin,
+        Some("example") => TargetKind::Example,
+        Some("test") => TargetKind::Test,
+        Some("bench") => TargetKind::Bench,
+        Some("custom-build") => TargetKind::BuildScript,
+        _ => TargetKind::Lib,
+    }
+}
+
+/// Crate info for a manifest with a `[package]` section. `workspace` is the enclosing
+/// workspace and its root directory, for inherited fields and dependencies.
+fn crate_info(
+    manifest: CargoToml,
+    path: &Path,
+    inclusion: Inclusion,
+    workspace: Option<(&Path, &Workspace)>,
+) -> Option<CrateInfo> {
+    let package = manifest.package?;
+    let targets = manifest_targets(&package, &manifest.targets, path);
+    let path_dependencies = path_dependencies(&manifest.dependencies, path, workspace);
+    let default = WorkspacePackage::default();
+    let inherited = workspace
+        .and_then(|(_, w)| w.package.as_ref())
+        .unwrap_or(&default);
+    Some(CrateInfo {
+        name: package.name,
+        path: path.to_path_buf(),
+        is_workspace_member: matches!(inclusion, Inclusion::Member { .. }),
+        inclusion,
+        is_default_member: true,
+        version: inherit(package.version, inherited.version.as_ref())
+            .unwrap_or_else(|| "0.0.0".to_string()),
+        description: inherit(package.description, inherited.description.as_ref()),
+        license: inherit(package.license, inherited.license.as_ref()),
+        authors: inherit(package.authors, inherited.authors.as_ref()).unwrap_or_default(),
+        repository: inherit(package.repository, inherited.repository.as_ref()),
+        edition: inherit(package.edition, inherited.edition.as_ref())
+            .unwrap_or_else(|| "2015".to_string()),
+        features: Vec::new(),
+        targets,
+        path_dependencies,
+    })
+}
+
+/// Directories of the `path` dependencies in a `[dependencies]` table, including
+/// `dep.workspace = true` entries that refer to a path in `[workspace.dependencies]`
+fn path_dependencies(
+    dependencies: &BTreeMap<String, toml::Value>,
+    crate_path: &Path,
+    workspace: Option<(&Path, &Workspace)>,
+) -> Vec<PathBuf> {
+    let path_of = |dependency: &toml::Value| dependency.get("path").and_then(|p| p.as_str()).map(str::to_string);
+    dependencies.iter()
+        .filter_map(|(name, dependency)| {
+            if let Some(path) = path_of(dependency) {
+                return Some(crate_path.join(path));
+            }
+            if dependency.get("workspace").and_then(|w| w.as_bool()) == Some(true) {
+                let (root, workspace) = workspace?;
+                return path_of(workspace.dependencies.get(name)?).map(|path| root.join(path));
+            }
+            None
+        })
+        .map(|path| normalize_path(&path))
+        .collect()
+}
+
+/// Resolve "." and ".." components without touching the file system
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// Build targets declared in a manifest, plus the ones cargo discovers automatically
+/// (`src/lib.rs`, `src/main.rs`, `src/bin/`, `examples/`, `tests/`, `benches/`, `build.rs`)
+/// unless disabled with `autobins = false` etc.
+fn manifest_targets(package: &Package, tables: &TargetTables, crate_path: &Path) -> Vec<CrateTarget> {
+    let mut targets = Vec::new();
+    let target = |kind, name: &str, path: &str| CrateTarget {
+        kind,
+        name: name.to_string(),
+        path: crate_path.join(path),
+    };
+    
+    let lib_name = package.name.replace('-', "_");
+    match &tables.lib {
+        Some(lib) => targets.push(target(
+            TargetKind::Lib,
+            lib.name.as_deref().unwrap_or(&lib_name),
+            lib.path.as_deref().unwrap_or("src/lib.rs"),
+        )),
+        None if crate_path.join("src/lib.rs").is_file() => {
+            targets.push(target(TargetKind::Lib, &lib_name, "src/lib.rs"));
+        }
+        None => {}
+    }
+    
+    // src/main.rs is the binary named after the package
+    let main_rs = crate_path.join("src/main.rs").is_file()
+        .then(|| (package.name.clone(), "src/main.rs".to_string()));
+    let kinds = [
+        (TargetKind::Bin, &tables.bin, "src/bin", package.autobins, main_rs),
+        (TargetKind::Example, &tables.example, "examples", package.autoexamples, None),
+        (TargetKind::Test, &tables.test, "tests", package.autotests, None),
+        (TargetKind::Bench, &tables.bench, "benches", package.autobenches, None),
+    ];
+    for (kind, entries, dir, auto, extra) in kinds {
+        let discovered: Vec<(String, String)> = extra.into_iter()
+            .chain(auto_targets(crate_path, dir))
+            .collect();
+        let start = targets.len();
+        for table in entries {
+            let Some(name) = &table.name else { continue };
+            let path = match &table.path {
+                Some(path) => path.clone(),
+                // An explicit entry without a path uses the file cargo would discover
+                None => match discovered.iter().find(|(n, _)| n == name) {
+                    Some((_, path)) => path.clone(),
+                    None => format!("{}/{}.rs", dir, name),
+                },
+            };
+            targets.push(target(kind, name, &path));
         }
+        if auto != Some(false) {
+            for (name, path) in &discovered {
+                let target = target(kind, name, path);
+                if !targets[start..].iter().any(|t| t.name == target.name || t.path == target.path) {
+                    targets.push(target);
+                }
+            }
+        }
+    }
+    
+    let build = match &package.build {
+        Some(BuildScript::Path(path)) => Some(path.as_str()),
+        Some(BuildScript::Enabled(false)) => None,
+        _ => crate_path.join("build.rs").is_file().then_some("build.rs"),
+    };
+    if let Some(path) = build {
+        targets.push(target(TargetKind::BuildScript, "build-script-build", path));
     }
     
-    Ok(crates)
+    targets
 }
 
-/// Try to parse a crate from a directory
-fn try_parse_crate(path: &Path) -> Result<Option<CrateInfo>> {
+/// Targets cargo discovers in a directory: `<dir>/<name>.rs` and `<dir>/<name>/main.rs`,
+/// as (name, relative path) sorted by name
+fn auto_targets(crate_path: &Path, dir: &str) -> Vec<(String, String)> {
+    let Ok(entries) = fs::read_dir(crate_path.join(dir)) else {
+        return Vec::new();
+    };
+    let mut found: Vec<(String, String)> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            let path = e.path();
+            if path.is_dir() {
+                path.join("main.rs").is_file()
+                    .then(|| (name.clone(), format!("{}/{}/main.rs", dir, name)))
+            } else {
+                name.strip_suffix(".rs")
+                    .map(|stem| (stem.to_string(), format!("{}/{}", dir, name)))
+            }
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+/// A workspace path pattern: a plain relative path, or a glob (`*`, `?`, `[abc]`, `**`)
+/// where `*` does not cross directory boundaries
+pub struct PathPattern {
+    pattern: String,
+    path: String,
+    glob: Option<GlobMatcher>,
+}
+
+impl PathPattern {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let path = normalize_pattern(pattern);
+        let glob = if is_glob(&path) {
+            let glob = GlobBuilder::new(&path)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid glob pattern '{}'", pattern))?;
+            Some(glob.compile_matcher())
+        } else {
+            None
+        };
+        Ok(Self { pattern: pattern.to_string(), path, glob })
+    }
+    
+    /// The pattern as written
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+    
+    /// Whether `rel_path` ("/"-separated, relative to the workspace root) matches
+    pub fn matches(&self, rel_path: &str) -> bool {
+        match &self.glob {
+            Some(glob) => glob.is_match(rel_path),
+            None => self.path == rel_path,
+        }
+    }
+    
+    /// Whether `rel_path` is the matched path or below it, compared by path component
+    pub fn contains(&self, rel_path: &str) -> bool {
+        let mut prefix = String::new();
+        for component in rel_path.split('/') {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(component);
+            if self.matches(&prefix) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Whether a pattern (or one of its components) uses glob syntax
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Normalize a pattern from Cargo.toml to "/"-separated components without "." or
+/// trailing slashes
+fn normalize_pattern(pattern: &str) -> String {
+    pattern.replace('\\', "/")
+        .split('/')
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// `path` relative to `root`, "/"-separated ("" for the root itself)
+pub fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .map(|p| p.components()
+            .filter_map(|c| match c {
+                Component::Normal(s) => Some(s.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/"))
+        .unwrap_or_default()
+}
+
+/// Expand a workspace member pattern to the matching directories
+fn expand_workspace_member(repo_path: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    let path_pattern = PathPattern::new(pattern)?;
+    
+    let Some(glob) = &path_pattern.glob else {
+        // Exact path
+        return Ok(vec![repo_path.join(&path_pattern.path)]);
+    };
+    
+    // Walk from the longest directory prefix without glob syntax, only as deep as the
+    // pattern reaches (unbounded for "**")
+    let components: Vec<&str> = path_pattern.path.split('/').collect();
+    let literal = components.iter().take_while(|c| !is_glob(c)).count();
+    let base = repo_path.join(components[..literal].join("/"));
+    let max_depth = if components.iter().any(|c| c.contains("**")) {
+        usize::MAX
+    } else {
+        components.len() - literal
+    };
+    
+    let mut dirs = Vec::new();
+    if base.is_dir() {
+        collect_dirs(&base, max_depth, &mut dirs)?;
+    }
+    let mut matched: Vec<PathBuf> = dirs.into_iter()
+        .filter(|d| glob.is_match(relative_path(repo_path, d)))
+        .collect();
+    matched.sort();
+    
+    Ok(matched)
+}
+
+/// Collect the directories below `dir` down to `max_depth` levels, skipping hidden
+/// directories, `target` and `node_modules`
+fn collect_dirs(dir: &Path, max_depth: usize, dirs: &mut Vec<PathBuf>) -> Result<()> {
+    if max_depth == 0 {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') || name == "target" || name == "node_modules" {
+            continue;
+        }
+        let path = entry.path();
+        collect_dirs(&path, max_depth - 1, dirs)?;
+        dirs.push(path);
+    }
+    Ok(())
+}
+
+/// Parse the Cargo.toml in a directory, if there is one
+fn try_parse_manifest(path: &Path) -> Result<Option<CargoToml>> {
     let cargo_path = path.join("Cargo.toml");
     
     if !cargo_path.exists() {
@@ -170,30 +823,28 @@ fn try_parse_crate(path: &Path) -> Result<Option<CrateInfo>> {
     }
     
     let content = fs::read_to_string(&cargo_path)
-        .context("Failed to read Cargo.toml")?;
+        .with_context(|| format!("Failed to read {}", cargo_path.display()))?;
     
     let cargo_toml: CargoToml = toml::from_str(&content)
-        .context("Failed to parse Cargo.toml")?;
-    
-    if let Some(package) = cargo_toml.package {
-        Ok(Some(CrateInfo {
-            name: package.name,
-            path: path.to_path_buf(),
-            is_workspace_member: false,
-            version: package.version,
-            description: package.description,
-        }))
-    } else {
-        Ok(None)
-    }
+        .with_context(|| format!("Failed to parse {}", cargo_path.display()))?;
+    
+    Ok(Some(cargo_toml))
+}
+
+/// Package name declared in a Cargo.toml, if it has a `[package]` section
+pub fn package_name(manifest: &str) -> Option<String> {
+    let cargo_toml: CargoToml = toml::from_str(manifest).ok()?;
+    cargo_toml.package.map(|p| p.name)
 }
 
-/// Recursively discover crates when there's no workspace, respecting .gitignore
-fn discover_crates_recursive(repo_path: &Path) -> Result<Vec<CrateInfo>> {
+/// Recursively discover crates when there's no root Cargo.toml, respecting .gitignore.
+///
+/// Nested workspace roots contribute their members (their other crates are skipped,
+/// cargo would not build them either), crates outside of any workspace are included
+/// unless they are below a directory matching `skip`.
+fn discover_crates_recursive(repo_path: &Path, skip: &[PathPattern], discovery: &mut Discovery) -> Result<()> {
     use ignore::WalkBuilder;
     
-    let mut crates = Vec::new();
-    
     let walker = WalkBuilder::new(repo_path)
         .hidden(true)           // Skip hidden files/directories
         .git_ignore(true)       // Respect .gitignore
@@ -203,6 +854,7 @@ fn discover_crates_recursive(repo_path: &Path) -> Result<Vec<CrateInfo>> {
         .follow_links(false)
         .build();
     
+    let mut manifest_dirs = Vec::new();
     for entry in walker {
         let entry = entry?;
         let path = entry.path();
@@ -216,25 +868,238 @@ fn discover_crates_recursive(repo_path: &Path) -> Result<Vec<CrateInfo>> {
         }
         
         if path.file_name().map(|n| n == "Cargo.toml").unwrap_or(false) {
-            let parent = path.parent().unwrap_or(repo_path);
-            if let Some(crate_info) = try_parse_crate(parent)? {
-                crates.push(crate_info);
+            manifest_dirs.push(path.parent().unwrap_or(repo_path).to_path_buf());
+        }
+    }
+    // Outer directories first, so nested workspaces are seen before their crates
+    manifest_dirs.sort_by_key(|dir| dir.components().count());
+    
+    let skipped_by = |rel_path: &str| skip.iter()
+        .find(|p| p.contains(rel_path))
+        .map(|p| format!("matches the skip pattern \"{}\"", p.pattern()));
+    
+    // Workspace roots found so far; their members are included when the root is
+    // reached, which comes before the members' own manifests
+    let mut workspace_roots: Vec<PathBuf> = Vec::new();
+    for dir in manifest_dirs {
+        let rel_path = relative_path(repo_path, &dir);
+        if discovery.crates.iter().any(|c| normalize_path(&c.path) == normalize_path(&dir)) {
+            continue;
+        }
+        if let Some(reason) = skipped_by(&rel_path) {
+            discovery.skipped.push((rel_path, reason));
+            continue;
+        }
+        let Some(mut manifest) = try_parse_manifest(&dir)? else {
+            continue;
+        };
+        if let Some(root) = workspace_roots.iter().find(|root| dir.starts_with(root)) {
+            if manifest.package.is_some() {
+                let reason = format!("inside the workspace in {} but not a member", relative_path(repo_path, root));
+                discovery.skipped.push((rel_path, reason));
             }
+            continue;
+        }
+        match manifest.workspace.take() {
+            Some(workspace) => {
+                discover_workspace(repo_path, &dir, manifest, &workspace, discovery)?;
+                workspace_roots.push(dir);
+            }
+            None => discovery.crates.extend(crate_info(manifest, &dir, Inclusion::Found, None)),
         }
     }
     
-    // Sort by name
-    crates.sort_by(|a, b| a.name.cmp(&b.name));
+    // Crates sharing a name would overwrite each other's PDF
+    let mut names: Vec<&CrateInfo> = discovery.crates.iter().collect();
+    names.sort_by(|a, b| a.name.cmp(&b.name));
+    for pair in names.windows(2) {
+        if pair[0].name == pair[1].name {
+            discovery.warnings.push(format!(
+                "Two crates are named {}: {} and {}",
+                pair[0].name,
+                relative_path(repo_path, &pair[0].path),
+                relative_path(repo_path, &pair[1].path),
+            ));
+        }
+    }
     
-    Ok(crates)
+    Ok(())
+}
+
+#[cfg(test)]
+impl CrateInfo {
+    /// A workspace member at `path` with no metadata, targets or dependencies, for tests
+    pub fn for_test(name: &str, path: PathBuf) -> Self {
+        CrateInfo {
+            name: name.to_string(),
+            path,
+            is_workspace_member: true,
+            inclusion: Inclusion::Found,
+            is_default_member: true,
+            version: "0.1.0".to_string(),
+            description: None,
+            license: None,
+            authors: Vec::new(),
+            repository: None,
+            edition: "2021".to_string(),
+            features: Vec::new(),
+            targets: Vec::new(),
+            path_dependencies: Vec::new(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::write_file;
     
     #[test]
-    fn test_discover_crates_single() {
-        // This would need a test fixture
+    fn test_package_name() {
+        assert_eq!(package_name("[package]\nname = \"foo\"\n").as_deref(), Some("foo"));
+        assert_eq!(package_name("[workspace]\nmembers = [\"a\"]\n"), None);
+    }
+
+    #[test]
+    fn test_path_pattern() {
+        let p = PathPattern::new("crates/*/sub").unwrap();
+        assert!(p.matches("crates/a/sub"));
+        assert!(!p.matches("crates/a/b/sub"));
+        assert!(PathPattern::new("crates/**").unwrap().matches("crates/a/b"));
+        assert!(PathPattern::new("crate-?").unwrap().matches("crate-a"));
+        assert!(PathPattern::new("[ab]x").unwrap().matches("bx"));
+        assert!(PathPattern::new("./crates/foo/").unwrap().matches("crates/foo"));
+
+        // exclude is a component-wise prefix, "crates/foo" does not exclude "crates/foobar"
+        let exclude = PathPattern::new("crates/foo").unwrap();
+        assert!(exclude.contains("crates/foo/inner"));
+        assert!(!exclude.contains("crates/foobar"));
+        assert!(PathPattern::new("crates/old-*").unwrap().contains("crates/old-a/b"));
+    }
+
+    #[test]
+    fn test_discover_workspace_globs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let write = |dir: &str, manifest: &str| write_file(root, Path::new(dir).join("Cargo.toml"), manifest);
+        write("", "[workspace]\nmembers = [\"crates/*/core\", \"tools/**\", \"missing\"]\n\
+                   exclude = [\"tools/old\"]\ndefault-members = [\"crates/*/core\"]\n");
+        write("crates/a/core", "[package]\nname = \"a-core\"\n");
+        write("crates/b/core", "[package]\nname = \"b-core\"\n");
+        write("tools/gen/cli", "[package]\nname = \"gen-cli\"\n");
+        write("tools/old", "[package]\nname = \"old\"\n");
+        fs::create_dir_all(root.join("tools/gen/cli/src")).unwrap();
+
+        let discovery = discover_crates(root, &[]).unwrap();
+
+        assert_eq!(discovery.root, RootManifest::VirtualWorkspace);
+        let names: Vec<_> = discovery.crates.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["a-core", "b-core", "gen-cli"]);
+        let defaults: Vec<_> = discovery.crates.iter().map(|c| c.is_default_member).collect();
+        assert_eq!(defaults, [true, true, false]);
+        assert_eq!(discovery.warnings, ["Workspace member missing has no Cargo.toml"]);
+    }
+
+    #[test]
+    fn test_discover_recursive() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let write = |dir: &str, manifest: &str| write_file(root, Path::new(dir).join("Cargo.toml"), manifest);
+        write("engine", "[workspace]\nmembers = [\"crates/*\", \"crates/a\"]\n[package]\nname = \"engine\"\n");
+        write("engine/crates/a", "[package]\nname = \"a\"\n");
+        write("engine/crates/b", "[package]\nname = \"b\"\n");
+        write("engine/tests/fixtures/broken", "[package]\nname = \"broken\"\n");
+        write("engine/examples/demo", "[package]\nname = \"demo\"\n");
+        write("tools/cli", "[package]\nname = \"cli\"\n");
+        write("tools/cli/tests/fixture", "[package]\nname = \"fixture\"\n");
+
+        let skip: Vec<String> = DEFAULT_SKIP_PATTERNS.split(',').map(String::from).collect();
+        let discovery = discover_crates(root, &skip).unwrap();
+
+        let included: Vec<_> = discovery.crates.iter()
+            .map(|c| (c.name.as_str(), c.inclusion.to_string()))
+            .collect();
+        assert_eq!(included, [
+            ("a", "member of the workspace in engine (\"crates/*\")".to_string()),
+            ("b", "member of the workspace in engine (\"crates/*\")".to_string()),
+            ("cli", "found outside of any workspace".to_string()),
+            ("engine", "root package of the workspace in engine".to_string()),
+        ]);
+        assert_eq!(discovery.skipped, [
+            ("engine/examples/demo".to_string(), "inside the workspace in engine but not a member".to_string()),
+            ("engine/tests/fixtures/broken".to_string(), "matches the skip pattern \"**/tests\"".to_string()),
+            ("tools/cli/tests/fixture".to_string(), "matches the skip pattern \"**/tests\"".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_manifest_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for file in ["src/lib.rs", "src/main.rs", "src/bin/a.rs", "src/bin/b/main.rs", "src/it.rs", "build.rs"] {
+            write_file(root, file, "");
+        }
+        let cargo_toml: CargoToml = toml::from_str(
+            "[package]\nname = \"my-tool\"\n[lib]\npath = \"src/lib.rs\"\n\
+             [[bin]]\nname = \"a\"\n[[test]]\nname = \"it\"\npath = \"src/it.rs\"\n",
+        ).unwrap();
+        let targets = manifest_targets(cargo_toml.package.as_ref().unwrap(), &cargo_toml.targets, root);
+
+        let found: Vec<_> = targets.iter()
+            .map(|t| (t.kind, t.name.as_str(), relative_path(root, &t.path)))
+            .collect();
+        assert_eq!(found, [
+            (TargetKind::Lib, "my_tool", "src/lib.rs".to_string()),
+            (TargetKind::Bin, "a", "src/bin/a.rs".to_string()),
+            (TargetKind::Bin, "my-tool", "src/main.rs".to_string()),
+            (TargetKind::Bin, "b", "src/bin/b/main.rs".to_string()),
+            (TargetKind::Test, "it", "src/it.rs".to_string()),
+            (TargetKind::BuildScript, "build-script-build", "build.rs".to_string()),
+        ]);
+
+        let cargo_toml: CargoToml = toml::from_str(
+            "[package]\nname = \"x\"\nautobins = false\nbuild = false\n",
+        ).unwrap();
+        // Only the library is still discovered
+        let kinds: Vec<_> = manifest_targets(cargo_toml.package.as_ref().unwrap(), &cargo_toml.targets, root)
+            .iter()
+            .map(|t| t.kind)
+            .collect();
+        assert_eq!(kinds, [TargetKind::Lib]);
+    }
+
+    #[test]
+    fn test_workspace_inheritance() {
+        let workspace: Workspace = toml::from_str(
+            "[package]\nversion = \"1.2.0\"\nedition = \"2021\"\nlicense = \"MIT\"\nauthors = [\"A <a@example.com>\"]\n\
+             [dependencies]\nbase = { path = \"crates/base\" }\n",
+        ).unwrap();
+        let cargo_toml: CargoToml = toml::from_str(
+            "[package]\nname = \"foo\"\nversion.workspace = true\nedition = { workspace = true }\n\
+             license = \"Apache-2.0\"\nauthors.workspace = true\nrepository.workspace = true\n\
+             [dependencies]\nbase.workspace = true\nutil = { path = \"../util\" }\nserde = \"1\"\n",
+        ).unwrap();
+        let root = Path::new("/repo");
+        let inclusion = Inclusion::Member { workspace: String::new(), pattern: "crates/*".to_string() };
+        let info = crate_info(cargo_toml, &root.join("crates/foo"), inclusion, Some((root, &workspace))).unwrap();
+        assert_eq!(info.version, "1.2.0");
+        assert_eq!(info.edition, "2021");
+        assert_eq!(info.license.as_deref(), Some("Apache-2.0"));
+        assert_eq!(info.authors, ["A <a@example.com>"]);
+        assert_eq!(info.repository, None);
+        assert_eq!(info.path_dependencies, [root.join("crates/base"), root.join("crates/util")]);
+
+        let cargo_toml: CargoToml = toml::from_str("[package]\nname = \"bar\"\n").unwrap();
+        let info = crate_info(cargo_toml, Path::new("bar"), Inclusion::Found, None).unwrap();
+        assert_eq!((info.version.as_str(), info.edition.as_str()), ("0.0.0", "2015"));
+    }
+
+    #[test]
+    fn test_target_kind() {
+        let kind = |k: &[&str]| target_kind(&k.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+        assert_eq!(kind(&["proc-macro"]), TargetKind::Lib);
+        assert_eq!(kind(&["rlib", "cdylib"]), TargetKind::Lib);
+        assert_eq!(kind(&["bin"]), TargetKind::Bin);
+        assert_eq!(kind(&["custom-build"]), TargetKind::BuildScript);
     }
 }
@@ -0,0 +1,134 @@
+//! Crate selection for `--crates`
+//!
+//! A comma-separated list of selectors: crate names or name globs (`azul-*`), paths
+//! relative to the repository root (anything containing a `/` or starting with `.`,
+//! e.g. `crates/core` or `./tools/*`), and exclusions of either prefixed with `!`.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobMatcher};
+
+use crate::crate_discovery::{relative_path, CrateInfo, PathPattern};
+
+/// Parsed `--crates` selectors
+pub struct CrateFilter {
+    include: Vec<Selector>,
+    exclude: Vec<Selector>,
+}
+
+enum Selector {
+    /// Crate name or name glob, `*` matches any characters
+    Name(String, GlobMatcher),
+    /// Crate directory (or a directory above it) relative to the repository root
+    Path(String, PathPattern),
+}
+
+impl Selector {
+    fn parse(spec: &str) -> Result<Self> {
+        if spec.contains('/') || spec.starts_with('.') {
+            Ok(Selector::Path(spec.to_string(), PathPattern::new(spec)?))
+        } else {
+            let glob = Glob::new(spec)
+                .with_context(|| format!("Invalid crate pattern '{}'", spec))?;
+            Ok(Selector::Name(spec.to_string(), glob.compile_matcher()))
+        }
+    }
+
+    /// `rel_path`: the crate directory relative to the repository root, "/"-separated
+    fn matches(&self, name: &str, rel_path: &str) -> bool {
+        match self {
+            Selector::Name(_, glob) => glob.is_match(name),
+            Selector::Path(_, pattern) => pattern.contains(rel_path),
+        }
+    }
+
+    fn spec(&self) -> &str {
+        match self {
+            Selector::Name(spec, _) | Selector::Path(spec, _) => spec,
+        }
+    }
+}
+
+impl CrateFilter {
+    /// Parse a comma-separated selector list such as `azul-*,!azul-dll,crates/tools`
+    pub fn parse(list: &str) -> Result<Self> {
+        Self::from_selectors(list.split(','))
+    }
+
+    pub fn from_selectors<'a>(selectors: impl IntoIterator<Item = &'a str>) -> Result<Self> {
+        let mut filter = Self { include: Vec::new(), exclude: Vec::new() };
+        for spec in selectors.into_iter().map(str::trim).filter(|s| !s.is_empty()) {
+            match spec.strip_prefix('!') {
+                Some(excluded) => filter.exclude.push(Selector::parse(excluded.trim())?),
+                None => filter.include.push(Selector::parse(spec)?),
+            }
+        }
+        Ok(filter)
+    }
+
+    /// Whether the crate `name` in `rel_path` (relative to the repository root,
+    /// "/"-separated) matches an include selector (or there are only exclusions) and
+    /// no exclusion
+    pub fn matches(&self, name: &str, rel_path: &str) -> bool {
+        let included = self.include.is_empty() || self.include.iter().any(|s| s.matches(name, rel_path));
+        included && !self.exclude.iter().any(|s| s.matches(name, rel_path))
+    }
+
+    /// Whether a crate matches an include selector (true if there are only exclusions)
+    pub fn includes(&self, crate_info: &CrateInfo, repo_path: &Path) -> bool {
+        let rel_path = relative_path(repo_path, &crate_info.path);
+        self.include.is_empty() || self.include.iter().any(|s| s.matches(&crate_info.name, &rel_path))
+    }
+
+    /// Whether a crate matches an exclusion
+    pub fn excludes(&self, crate_info: &CrateInfo, repo_path: &Path) -> bool {
+        let rel_path = relative_path(repo_path, &crate_info.path);
+        self.exclude.iter().any(|s| s.matches(&crate_info.name, &rel_path))
+    }
+
+    /// Include selectors that match none of `crates`, most likely typos
+    pub fn unmatched<'a>(&'a self, crates: &[CrateInfo], repo_path: &Path) -> Vec<&'a str> {
+        self.include.iter()
+            .filter(|s| !crates.iter().any(|c| s.matches(&c.name, &relative_path(repo_path, &c.path))))
+            .map(|s| s.spec())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crate_at(name: &str, dir: &str) -> CrateInfo {
+        CrateInfo::for_test(name, Path::new("/repo").join(dir))
+    }
+
+    #[test]
+    fn test_filter() {
+        let root = Path::new("/repo");
+        let crates = [
+            crate_at("azul-core", "core"),
+            crate_at("azul-dll", "dll"),
+            crate_at("azul-layout", "layout"),
+            crate_at("gen", "tools/gen"),
+        ];
+        let selected = |list: &str| {
+            let filter = CrateFilter::parse(list).unwrap();
+            crates.iter()
+                .filter(|c| filter.includes(c, root) && !filter.excludes(c, root))
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(selected("azul-*,!azul-dll"), ["azul-core", "azul-layout"]);
+        assert_eq!(selected("!azul-*"), ["gen"]);
+        assert_eq!(selected("tools/, azul-core"), ["azul-core", "gen"]);
+        assert_eq!(selected("./tools/gen"), ["gen"]);
+        assert_eq!(selected("!./tools"), ["azul-core", "azul-dll", "azul-layout"]);
+
+        let filter = CrateFilter::parse("azul-core,azul-cor").unwrap();
+        assert_eq!(filter.unmatched(&crates, root), ["azul-cor"]);
+        assert!(filter.matches("azul-core", "core"));
+        assert!(!CrateFilter::parse("!core").unwrap().matches("core", ""));
+    }
+}
@@ -0,0 +1,269 @@
+//! Dependency graph between the crates of a workspace
+//!
+//! Edges come from `path` dependencies in each crate's `[dependencies]`. The graph
+//! orders a combined printout (dependencies before the crates using them) and is
+//! drawn as a layered SVG diagram on the workspace overview page.
+
+use std::collections::BTreeSet;
+
+use crate::crate_discovery::{normalize_path, CrateInfo};
+
+/// Path dependencies between a set of crates
+pub struct DependencyGraph<'a> {
+    crates: Vec<&'a CrateInfo>,
+    /// `dependencies[i]`: indices of the crates crate `i` depends on
+    dependencies: Vec<Vec<usize>>,
+}
+
+impl<'a> DependencyGraph<'a> {
+    /// Build the graph between `crates`, ignoring dependencies on crates outside of it
+    pub fn new(crates: &[&'a CrateInfo]) -> Self {
+        let paths: Vec<_> = crates.iter().map(|c| normalize_path(&c.path)).collect();
+        let dependencies = crates.iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let mut deps: Vec<usize> = c.path_dependencies.iter()
+                    .filter_map(|dep| paths.iter().position(|p| p == dep))
+                    .filter(|&d| d != i)
+                    .collect();
+                deps.sort_unstable();
+                deps.dedup();
+                deps
+            })
+            .collect();
+        Self { crates: crates.to_vec(), dependencies }
+    }
+
+    pub fn crates(&self) -> &[&'a CrateInfo] {
+        &self.crates
+    }
+
+    /// Crates that crate `i` depends on
+    pub fn dependencies(&self, i: usize) -> &[usize] {
+        &self.dependencies[i]
+    }
+
+    /// Crates that depend on crate `i`
+    pub fn dependents(&self, i: usize) -> Vec<usize> {
+        (0..self.crates.len())
+            .filter(|&j| self.dependencies[j].contains(&i))
+            .collect()
+    }
+
+    /// `selected` plus everything they depend on, directly or transitively, in index order
+    pub fn with_dependencies(&self, selected: &[usize]) -> Vec<usize> {
+        let mut included = vec![false; self.crates.len()];
+        let mut stack = selected.to_vec();
+        while let Some(i) = stack.pop() {
+            if !included[i] {
+                included[i] = true;
+                stack.extend_from_slice(&self.dependencies[i]);
+            }
+        }
+        (0..self.crates.len()).filter(|&i| included[i]).collect()
+    }
+
+    /// Whether any crate depends on another
+    pub fn has_edges(&self) -> bool {
+        self.dependencies.iter().any(|d| !d.is_empty())
+    }
+
+    /// Crate indices ordered so that every crate comes after its dependencies.
+    /// Ties are broken by name; crates in a dependency cycle are ordered by name.
+    pub fn topological_order(&self) -> Vec<usize> {
+        let n = self.crates.len();
+        let mut remaining: Vec<usize> = self.dependencies.iter().map(|d| d.len()).collect();
+        let mut placed = vec![false; n];
+        let key = |i: usize| (self.crates[i].name.as_str(), i);
+        let mut ready: BTreeSet<_> = (0..n).filter(|&i| remaining[i] == 0).map(key).collect();
+        let mut order = Vec::with_capacity(n);
+
+        while order.len() < n {
+            let next = match ready.pop_first() {
+                Some((_, i)) => i,
+                // Only cycles are left: break one at the first crate by name
+                None => (0..n).filter(|&i| !placed[i]).min_by_key(|&i| key(i)).unwrap(),
+            };
+            if placed[next] {
+                continue;
+            }
+            placed[next] = true;
+            order.push(next);
+            for dependent in self.dependents(next) {
+                if !placed[dependent] {
+                    remaining[dependent] -= 1;
+                    if remaining[dependent] == 0 {
+                        ready.insert(key(dependent));
+                    }
+                }
+            }
+        }
+        order
+    }
+
+    /// Layer of each crate: 0 without dependencies, otherwise one more than the highest
+    /// layer of its dependencies (dependencies closing a cycle are ignored)
+    pub fn layers(&self) -> Vec<usize> {
+        let mut layers: Vec<Option<usize>> = vec![None; self.crates.len()];
+        for i in self.topological_order() {
+            layers[i] = Some(self.dependencies[i].iter()
+                .filter_map(|&d| layers[d])
+                .map(|layer| layer + 1)
+                .max()
+                .unwrap_or(0));
+        }
+        layers.into_iter().map(|l| l.unwrap_or(0)).collect()
+    }
+
+    /// Draw the graph as an SVG diagram: one row per layer, crates using others above
+    /// the crates they depend on, arrows pointing at dependencies
+    pub fn render_svg(&self) -> String {
+        const CHAR_WIDTH: f32 = 7.2;
+        const BOX_HEIGHT: f32 = 28.0;
+        const H_GAP: f32 = 20.0;
+        const V_GAP: f32 = 50.0;
+
+        let layers = self.layers();
+        let layer_count = layers.iter().max().map_or(0, |&l| l + 1);
+        let box_width = self.crates.iter()
+            .map(|c| c.name.chars().count())
+            .max()
+            .unwrap_or(0) as f32 * CHAR_WIDTH + 20.0;
+
+        // Rows from the foundations up; each row is ordered by the mean position of the
+        // dependencies below it, which keeps most arrows short and uncrossed
+        let mut rows: Vec<Vec<usize>> = vec![Vec::new(); layer_count];
+        let mut column = vec![0.0f32; self.crates.len()];
+        for (layer, row) in rows.iter_mut().enumerate() {
+            let mut members: Vec<(f32, &str, usize)> = (0..self.crates.len())
+                .filter(|&i| layers[i] == layer)
+                .map(|i| {
+                    let deps = &self.dependencies[i];
+                    let mean = if deps.is_empty() {
+                        0.0
+                    } else {
+                        deps.iter().map(|&d| column[d]).sum::<f32>() / deps.len() as f32
+                    };
+                    (mean, self.crates[i].name.as_str(), i)
+                })
+                .collect();
+            members.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(b.1)));
+            for (position, &(_, _, i)) in members.iter().enumerate() {
+                column[i] = position as f32;
+                row.push(i);
+            }
+        }
+
+        let widest = rows.iter().map(|r| r.len()).max().unwrap_or(0) as f32;
+        let width = widest * (box_width + H_GAP) + H_GAP;
+        let height = layer_count as f32 * (BOX_HEIGHT + V_GAP) - V_GAP + 2.0 * H_GAP;
+
+        // Top-left corner of each crate's box; the top row holds the highest layer
+        let mut position = vec![(0.0f32, 0.0f32); self.crates.len()];
+        for (layer, row) in rows.iter().enumerate() {
+            let offset = (width - row.len() as f32 * (box_width + H_GAP) + H_GAP) / 2.0;
+            let y = H_GAP + (layer_count - 1 - layer) as f32 * (BOX_HEIGHT + V_GAP);
+            for (k, &i) in row.iter().enumerate() {
+                position[i] = (offset + k as f32 * (box_width + H_GAP), y);
+            }
+        }
+
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.0}" height="{h:.0}" viewBox="0 0 {w:.0} {h:.0}">
+"#,
+            w = width,
+            h = height,
+        );
+        for (i, deps) in self.dependencies.iter().enumerate() {
+            for &d in deps {
+                let (x1, y1) = (position[i].0 + box_width / 2.0, position[i].1 + BOX_HEIGHT);
+                let (x2, y2) = (position[d].0 + box_width / 2.0, position[d].1);
+                // A dependency in the same or a higher layer (cycle) is entered from below
+                let y2 = if y2 < y1 { y2 + BOX_HEIGHT } else { y2 };
+                svg.push_str(&arrow(x1, y1, x2, y2));
+            }
+        }
+        for (i, c) in self.crates.iter().enumerate() {
+            let (x, y) = position[i];
+            svg.push_str(&format!(
+                r##"<rect x="{x:.1}" y="{y:.1}" width="{bw:.1}" height="{bh:.1}" rx="4" fill="#f4f4f4" stroke="#333"/>
+<text x="{tx:.1}" y="{ty:.1}" text-anchor="middle" font-family="monospace" font-size="12" fill="#222">{name}</text>
+"##,
+                x = x,
+                y = y,
+                bw = box_width,
+                bh = BOX_HEIGHT,
+                tx = x + box_width / 2.0,
+                ty = y + BOX_HEIGHT / 2.0 + 4.0,
+                name = xml_escape(&c.name),
+            ));
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// A line from (x1, y1) to (x2, y2) with an arrowhead at the end
+fn arrow(x1: f32, y1: f32, x2: f32, y2: f32) -> String {
+    const HEAD: f32 = 7.0;
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let len = (dx * dx + dy * dy).sqrt().max(1.0);
+    let (ux, uy) = (dx / len, dy / len);
+    let (bx, by) = (x2 - ux * HEAD, y2 - uy * HEAD);
+    format!(
+        r##"<line x1="{x1:.1}" y1="{y1:.1}" x2="{bx:.1}" y2="{by:.1}" stroke="#888" stroke-width="1.2"/>
+<polygon points="{x2:.1},{y2:.1} {lx:.1},{ly:.1} {rx:.1},{ry:.1}" fill="#888"/>
+"##,
+        lx = bx - uy * HEAD / 2.0,
+        ly = by + ux * HEAD / 2.0,
+        rx = bx + uy * HEAD / 2.0,
+        ry = by - ux * HEAD / 2.0,
+    )
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    fn crate_at(name: &str, deps: &[&str]) -> CrateInfo {
+        let root = Path::new("/ws");
+        CrateInfo {
+            path_dependencies: deps.iter().map(|d| root.join(d)).collect::<Vec<PathBuf>>(),
+            ..CrateInfo::for_test(name, root.join(name))
+        }
+    }
+
+    #[test]
+    fn test_topological_order() {
+        let crates = [
+            crate_at("app", &["core", "util"]),
+            crate_at("core", &["util", "../external"]),
+            crate_at("util", &[]),
+            crate_at("zeta", &[]),
+        ];
+        let refs: Vec<&CrateInfo> = crates.iter().collect();
+        let graph = DependencyGraph::new(&refs);
+        let names: Vec<_> = graph.topological_order().iter().map(|&i| crates[i].name.as_str()).collect();
+        assert_eq!(names, ["util", "core", "app", "zeta"]);
+        assert_eq!(graph.layers(), [2, 1, 0, 0]);
+        assert_eq!(graph.dependents(2), [0, 1]);
+        assert_eq!(graph.with_dependencies(&[1]), [1, 2]);
+    }
+
+    #[test]
+    fn test_cycle() {
+        let crates = [crate_at("b", &["a"]), crate_at("a", &["b"]), crate_at("c", &["a"])];
+        let refs: Vec<&CrateInfo> = crates.iter().collect();
+        let graph = DependencyGraph::new(&refs);
+        let names: Vec<_> = graph.topological_order().iter().map(|&i| crates[i].name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert!(graph.render_svg().starts_with("<svg"));
+    }
+}
@@ -9,6 +9,9 @@ use std::path::{Path, PathBuf};
 use anyhow::Result;
 use ignore::WalkBuilder;
 
+use crate::crate_discovery::{CrateTarget, TargetKind};
+use crate::file_content::is_binary;
+
 /// Category of a source file
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FileCategory {
@@ -39,12 +42,29 @@ pub struct SourceFile {
     pub category: FileCategory,
     /// Module path (e.g., "crate::foo::bar")
     pub module_path: String,
+    /// Binary target the file belongs to, printed as its own section (None for the
+    /// library, and for crates with a single library or binary target)
+    pub section: Option<String>,
 }
 
-/// Classify all Rust files in a crate, respecting .gitignore
-pub fn classify_files(crate_path: &Path, include_tests: bool) -> Result<Vec<SourceFile>> {
+/// Classify all Rust files in a crate, respecting .gitignore. Files are classified by
+/// the crate's build targets if known, otherwise by their directory.
+///
+/// With `binary_fixtures`, binary files of at most that many bytes in `tests`
+/// directories (test fixtures) are included too, to be printed as a hex dump.
+pub fn classify_files(
+    crate_path: &Path,
+    targets: &[CrateTarget],
+    include_tests: bool,
+    binary_fixtures: Option<u64>,
+) -> Result<Vec<SourceFile>> {
     let mut files = Vec::new();
     
+    // Binaries get their own sections if the crate has more than one library or binary target
+    let has_sections = targets.iter()
+        .filter(|t| matches!(t.kind, TargetKind::Lib | TargetKind::Bin))
+        .count() > 1;
+    
     // Use ignore crate's WalkBuilder which respects .gitignore
     let walker = WalkBuilder::new(crate_path)
         .hidden(true)           // Skip hidden files/directories
@@ -64,21 +84,29 @@ pub fn classify_files(crate_path: &Path, include_tests: bool) -> Result<Vec<Sour
             continue;
         }
         
-        // Only process Rust files
-        if path.extension().map(|e| e != "rs").unwrap_or(true) {
-            continue;
-        }
-        
         // Skip target directory explicitly (in case it's not in .gitignore)
         let relative_path = path.strip_prefix(crate_path)
             .unwrap_or(path)
             .to_path_buf();
         
+        // Only process Rust files, and binary test fixtures if requested
+        let is_rust = path.extension().is_some_and(|e| e == "rs");
+        if !is_rust && !binary_fixtures.is_some_and(|limit| is_binary_fixture(path, &relative_path, limit)) {
+            continue;
+        }
+        
         if relative_path.components().any(|c| c.as_os_str() == "target") {
             continue;
         }
         
-        let category = classify_file(&relative_path);
+        let target = owning_target(crate_path, &relative_path, targets);
+        let category = match target {
+            Some(t) => target_category(t.kind, &relative_path),
+            None => classify_file(&relative_path),
+        };
+        let section = target
+            .filter(|t| t.kind == TargetKind::Bin && has_sections)
+            .map(|t| t.name.clone());
         
         // Skip tests if not included
         if !include_tests && matches!(category, FileCategory::Test | FileCategory::IntegrationTest) {
@@ -92,15 +120,122 @@ pub fn classify_files(crate_path: &Path, include_tests: bool) -> Result<Vec<Sour
             relative_path,
             category,
             module_path,
+            section,
         });
     }
     
-    // Sort files by their path for consistent ordering
-    files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+    // Sort files by their path for consistent ordering, the library before binary sections
+    // and each binary's root file first in its section
+    files.sort_by_key(|f| {
+        let is_root = targets.iter().any(|t| t.path == f.path);
+        (f.section.clone(), f.section.is_some() && !is_root, f.relative_path.clone())
+    });
     
     Ok(files)
 }
 
+/// Whether `path` is a binary file of at most `limit` bytes in a `tests` directory
+fn is_binary_fixture(path: &Path, relative_path: &Path, limit: u64) -> bool {
+    relative_path.components().any(|c| c.as_os_str() == "tests")
+        && fs::metadata(path).is_ok_and(|m| m.len() <= limit)
+        && fs::read(path).is_ok_and(|bytes| is_binary(&bytes))
+}
+
+/// Image file extensions that can be embedded as figures
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "bmp", "svg"];
+
+/// Directories whose images are not figures: build output and test fixtures
+const NON_FIGURE_DIRS: &[&str] = &["target", "tests", "fixtures", "testdata", "test-data"];
+
+/// An image file found in a crate or repository
+#[derive(Debug, Clone)]
+pub struct ImageFile {
+    /// Absolute path to the file
+    pub path: PathBuf,
+    /// Path relative to the searched directory
+    pub relative_path: PathBuf,
+}
+
+/// Find all image files below `dir` (e.g. diagrams under docs/ or assets/), respecting
+/// .gitignore. Build output, test fixtures and the directories in `skip` (e.g. the
+/// directories of other crates) are not searched.
+pub fn find_image_files(dir: &Path, skip: &[PathBuf]) -> Result<Vec<ImageFile>> {
+    let mut images = Vec::new();
+    
+    let skip = skip.to_vec();
+    let walker = WalkBuilder::new(dir)
+        .hidden(true)
+        .git_ignore(true)
+        .git_global(true)
+        .git_exclude(true)
+        .parents(true)
+        .follow_links(false)
+        .filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            !(entry.depth() > 0 && is_dir && (
+                NON_FIGURE_DIRS.iter().any(|d| entry.file_name() == *d)
+                    || skip.iter().any(|s| s == entry.path())
+            ))
+        })
+        .build();
+    
+    for entry in walker {
+        let entry = entry?;
+        let path = entry.path();
+        
+        if !path.is_file() || !is_image_file(path) {
+            continue;
+        }
+        
+        images.push(ImageFile {
+            path: path.to_path_buf(),
+            relative_path: path.strip_prefix(dir).unwrap_or(path).to_path_buf(),
+        });
+    }
+    
+    images.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+    
+    Ok(images)
+}
+
+/// Check if a path has an image extension
+fn is_image_file(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .map(|e| IMAGE_EXTENSIONS.contains(&e.as_str()))
+        .unwrap_or(false)
+}
+
+/// The build target a file belongs to: the target whose root file it is, or whose root
+/// file's directory (other than the crate root) contains it most closely. Files shared by
+/// the library and a binary (e.g. `src/` with `src/lib.rs` and `src/main.rs`) belong to
+/// the library.
+fn owning_target<'a>(crate_path: &Path, relative_path: &Path, targets: &'a [CrateTarget]) -> Option<&'a CrateTarget> {
+    let path = crate_path.join(relative_path);
+    targets.iter()
+        .find(|t| t.path == path)
+        .or_else(|| targets.iter()
+            .filter_map(|t| t.path.parent().map(|dir| (t, dir)))
+            .filter(|(_, dir)| *dir != crate_path && path.starts_with(dir))
+            .max_by_key(|(t, dir)| (dir.components().count(), t.kind == TargetKind::Lib))
+            .map(|(t, _)| t))
+}
+
+/// Category of a file belonging to a target of the given kind
+fn target_category(kind: TargetKind, relative_path: &Path) -> FileCategory {
+    match kind {
+        // Keep unit test modules in a tests/ directory of the library apart
+        TargetKind::Lib | TargetKind::Bin if relative_path.components().any(|c| c.as_os_str() == "tests") => {
+            FileCategory::Test
+        }
+        TargetKind::Lib | TargetKind::Bin => FileCategory::Source,
+        TargetKind::Example => FileCategory::Example,
+        TargetKind::Test => FileCategory::IntegrationTest,
+        TargetKind::Bench => FileCategory::Benchmark,
+        TargetKind::BuildScript => FileCategory::BuildScript,
+    }
+}
+
 /// Classify a file based on its relative path
 fn classify_file(relative_path: &Path) -> FileCategory {
     let components: Vec<_> = relative_path.components()
@@ -183,6 +318,7 @@ pub fn file_contains_tests(path: &Path) -> Result<bool> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::write_file;
     
     #[test]
     fn test_classify_source() {
@@ -202,6 +338,53 @@ mod tests {
         assert_eq!(classify_file(Path::new("examples/demo.rs")), FileCategory::Example);
     }
     
+    #[test]
+    fn test_owning_target() {
+        let root = Path::new("/repo/foo");
+        let target = |kind, name: &str, path: &str| CrateTarget { kind, name: name.to_string(), path: root.join(path) };
+        let targets = [
+            target(TargetKind::Lib, "foo", "src/lib.rs"),
+            target(TargetKind::Test, "it", "src/it.rs"),
+            target(TargetKind::Bin, "foo", "src/main.rs"),
+            target(TargetKind::Bin, "tool", "src/bin/tool/main.rs"),
+            target(TargetKind::Example, "hello", "demos/hello/main.rs"),
+        ];
+        let owner = |p: &str| owning_target(root, Path::new(p), &targets).map(|t| (t.kind, t.name.as_str()));
+        assert_eq!(owner("src/parser.rs"), Some((TargetKind::Lib, "foo")));
+        assert_eq!(owner("src/main.rs"), Some((TargetKind::Bin, "foo")));
+        assert_eq!(owner("src/it.rs"), Some((TargetKind::Test, "it")));
+        assert_eq!(owner("src/bin/tool/args.rs"), Some((TargetKind::Bin, "tool")));
+        assert_eq!(owner("demos/hello/util.rs"), Some((TargetKind::Example, "hello")));
+        assert_eq!(owner("scripts/gen.rs"), None);
+        assert_eq!(target_category(TargetKind::Test, Path::new("src/it.rs")), FileCategory::IntegrationTest);
+    }
+    
+    #[test]
+    fn test_is_image_file() {
+        assert!(is_image_file(Path::new("docs/architecture.svg")));
+        assert!(is_image_file(Path::new("assets/logo.PNG")));
+        assert!(!is_image_file(Path::new("src/lib.rs")));
+    }
+    
+    #[test]
+    fn test_find_image_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for file in [
+            "docs/architecture.svg", "assets/logo.png", "crates/a/docs/a.png", "tests/fixtures/input.png",
+            "crates/a/tests/data/x.png", "target/doc/logo.png", "testdata/y.png", "src/lib.rs",
+        ] {
+            write_file(root, file, "");
+        }
+        let found = |dir: &Path, skip: &[PathBuf]| -> Vec<String> {
+            find_image_files(dir, skip).unwrap().iter()
+                .map(|image| image.relative_path.to_string_lossy().replace('\\', "/"))
+                .collect()
+        };
+        assert_eq!(found(root, &[root.join("crates/a")]), ["assets/logo.png", "docs/architecture.svg"]);
+        assert_eq!(found(&root.join("crates/a"), &[]), ["docs/a.png"]);
+    }
+    
     #[test]
     fn test_module_path() {
         assert_eq!(compute_module_path(Path::new("src/lib.rs")), "crate");
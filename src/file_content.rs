@@ -0,0 +1,254 @@
+//! Loading file content for rendering
+//!
+//! Detects binary files and decodes text that is not valid UTF-8 (e.g. Latin-1, or
+//! UTF-16/UTF-32 from Windows tools) lossily, so a single odd file doesn't drop out of
+//! the printout.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chardetng::EncodingDetector;
+use encoding_rs::{UTF_16BE, UTF_16LE};
+
+/// Number of leading bytes inspected by the binary heuristic
+const BINARY_SNIFF_LEN: usize = 8192;
+
+/// Content of a file, ready for rendering
+#[derive(Debug, Clone)]
+pub enum FileContent {
+    /// Text content
+    Text {
+        content: String,
+        /// Name of the detected encoding if the file was not valid UTF-8
+        decoded_from: Option<&'static str>,
+    },
+    /// Binary content, rendered as a placeholder or hex dump
+    Binary {
+        bytes: Vec<u8>,
+    },
+}
+
+/// Read a file and decide whether it is text or binary
+pub fn load_file(path: &Path) -> io::Result<FileContent> {
+    let bytes = fs::read(path)?;
+    Ok(classify_bytes(bytes))
+}
+
+/// Classify raw bytes as text (decoding if necessary) or binary
+pub fn classify_bytes(bytes: Vec<u8>) -> FileContent {
+    if let Some(encoding) = WideEncoding::detect(&bytes) {
+        return FileContent::Text { content: encoding.decode(&bytes), decoded_from: Some(encoding.name()) };
+    }
+    if is_binary(&bytes) {
+        return FileContent::Binary { bytes };
+    }
+
+    match String::from_utf8(bytes) {
+        Ok(content) => FileContent::Text { content, decoded_from: None },
+        Err(e) => {
+            let bytes = e.into_bytes();
+            let (content, encoding) = decode_lossy(&bytes);
+            FileContent::Text { content, decoded_from: Some(encoding) }
+        }
+    }
+}
+
+/// Heuristic binary detection: a NUL byte, or a high share of control
+/// characters, in the first few kilobytes. UTF-16 and UTF-32 text is not binary.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(BINARY_SNIFF_LEN)];
+    if sample.is_empty() || WideEncoding::detect(sample).is_some() {
+        return false;
+    }
+    if sample.contains(&0) {
+        return true;
+    }
+    let control = sample.iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\n' | b'\r' | b'\t' | 0x0c))
+        .count();
+    control * 10 > sample.len()
+}
+
+/// Unicode encodings with code units wider than a byte, whose text is full of NUL bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WideEncoding {
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl WideEncoding {
+    /// Detect the encoding from a byte order mark or, without one, from NUL bytes
+    /// evenly interleaved with (mostly ASCII) text
+    fn detect(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0xff, 0xfe, 0, 0, ..] => return Some(Self::Utf32Le),
+            [0, 0, 0xfe, 0xff, ..] => return Some(Self::Utf32Be),
+            [0xff, 0xfe, ..] => return Some(Self::Utf16Le),
+            [0xfe, 0xff, ..] => return Some(Self::Utf16Be),
+            _ => {}
+        }
+        let sample = &bytes[..bytes.len().min(BINARY_SNIFF_LEN)];
+        // Share of NUL bytes at each position within 4-byte groups, in percent
+        let units = sample.len() / 4;
+        if units < 2 {
+            return None;
+        }
+        let nul_share: Vec<usize> = (0..4)
+            .map(|offset| sample.chunks_exact(4).filter(|unit| unit[offset] == 0).count() * 100 / units)
+            .collect();
+        let (nul, text) = (|share: usize| share >= 90, |share: usize| share <= 10);
+        match nul_share[..] {
+            [a, b, c, d] if text(a) && nul(b) && nul(c) && nul(d) => Some(Self::Utf32Le),
+            [a, b, c, d] if nul(a) && nul(b) && nul(c) && text(d) => Some(Self::Utf32Be),
+            [a, b, c, d] if text(a) && nul(b) && text(c) && nul(d) => Some(Self::Utf16Le),
+            [a, b, c, d] if nul(a) && text(b) && nul(c) && text(d) => Some(Self::Utf16Be),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Utf16Le => "UTF-16LE",
+            Self::Utf16Be => "UTF-16BE",
+            Self::Utf32Le => "UTF-32LE",
+            Self::Utf32Be => "UTF-32BE",
+        }
+    }
+
+    /// Decode the text, dropping a byte order mark; invalid code units become U+FFFD
+    fn decode(self, bytes: &[u8]) -> String {
+        match self {
+            // encoding_rs removes a BOM
+            Self::Utf16Le => UTF_16LE.decode(bytes).0.into_owned(),
+            Self::Utf16Be => UTF_16BE.decode(bytes).0.into_owned(),
+            // encoding_rs has no UTF-32 (it is not a web encoding)
+            Self::Utf32Le | Self::Utf32Be => bytes.chunks(4)
+                .map(|unit| {
+                    let unit: [u8; 4] = unit.try_into().unwrap_or([0xff; 4]);
+                    let code = if self == Self::Utf32Le { u32::from_le_bytes(unit) } else { u32::from_be_bytes(unit) };
+                    char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                })
+                .skip_while(|&c| c == '\u{feff}')
+                .collect(),
+        }
+    }
+}
+
+/// Decode non-UTF-8 text using the most likely legacy encoding.
+/// Returns the decoded text and the name of the encoding used.
+fn decode_lossy(bytes: &[u8]) -> (String, &'static str) {
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    let encoding = detector.guess(None, true);
+    let (content, _, _) = encoding.decode(bytes);
+    (content.into_owned(), encoding.name())
+}
+
+/// Human-readable size, e.g. "12 KB"
+pub fn format_size(len: u64) -> String {
+    if len < 1024 {
+        format!("{} B", len)
+    } else if len < 1024 * 1024 {
+        format!("{} KB", len.div_ceil(1024))
+    } else {
+        format!("{:.1} MB", len as f64 / (1024.0 * 1024.0))
+    }
+}
+
+/// Render bytes as a classic hex dump (offset, 16 hex bytes, ASCII column)
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (row, chunk) in bytes.chunks(16).enumerate() {
+        out.push_str(&format!("{:08x}  ", row * 16));
+        for i in 0..16 {
+            match chunk.get(i) {
+                Some(b) => out.push_str(&format!("{:02x} ", b)),
+                None => out.push_str("   "),
+            }
+            if i == 7 {
+                out.push(' ');
+            }
+        }
+        out.push(' ');
+        for &b in chunk {
+            out.push(if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' });
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8_is_text() {
+        match classify_bytes("fn main() {}\n".as_bytes().to_vec()) {
+            FileContent::Text { content, decoded_from } => {
+                assert_eq!(content, "fn main() {}\n");
+                assert!(decoded_from.is_none());
+            }
+            FileContent::Binary { .. } => panic!("expected text"),
+        }
+    }
+
+    #[test]
+    fn test_latin1_is_decoded() {
+        // "// Grüße" in Latin-1
+        let bytes = b"// Gr\xfc\xdfe\n".to_vec();
+        match classify_bytes(bytes) {
+            FileContent::Text { content, decoded_from } => {
+                assert_eq!(content, "// Grüße\n");
+                assert!(decoded_from.is_some());
+            }
+            FileContent::Binary { .. } => panic!("expected text"),
+        }
+    }
+
+    #[test]
+    fn test_utf16_and_utf32_are_decoded() {
+        let utf16le: Vec<u8> = [0xff, 0xfe].into_iter()
+            .chain("fn main() {}\n".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        let utf16be: Vec<u8> = "// Grüße\n".encode_utf16().flat_map(u16::to_be_bytes).collect();
+        let utf32le: Vec<u8> = "\u{feff}ok\n".chars().flat_map(|c| (c as u32).to_le_bytes()).collect();
+        for (bytes, expected, encoding) in [
+            (utf16le, "fn main() {}\n", "UTF-16LE"),
+            (utf16be, "// Grüße\n", "UTF-16BE"),
+            (utf32le, "ok\n", "UTF-32LE"),
+        ] {
+            assert!(!is_binary(&bytes));
+            match classify_bytes(bytes) {
+                FileContent::Text { content, decoded_from } => {
+                    assert_eq!(content, expected);
+                    assert_eq!(decoded_from, Some(encoding));
+                }
+                FileContent::Binary { .. } => panic!("expected {} text", encoding),
+            }
+        }
+    }
+
+    #[test]
+    fn test_binary_detection() {
+        assert!(is_binary(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+        assert!(!is_binary(b"plain text\n"));
+        assert!(!is_binary(b""));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(12 * 1024), "12 KB");
+    }
+
+    #[test]
+    fn test_hex_dump() {
+        let dump = hex_dump(b"AB\0");
+        assert!(dump.starts_with("00000000  41 42 00 "));
+        assert!(dump.trim_end().ends_with("AB."));
+    }
+}
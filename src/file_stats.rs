@@ -0,0 +1,247 @@
+//! Per-file statistics for the summary page (`--summary`) and JSON output (`--json`)
+//!
+//! Statistics are taken from the printed text (after formatting and `--public-api`), so
+//! they describe what the reader holds in their hands.
+
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use serde::Serialize;
+
+use crate::item_index::index_items;
+
+/// Line and item counts of one file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct FileStats {
+    /// All lines
+    pub lines: usize,
+    /// Lines with code (possibly followed by a comment)
+    pub code: usize,
+    /// Lines with only comments (including doc comments)
+    pub comments: usize,
+    /// Empty or whitespace-only lines
+    pub blank: usize,
+    /// Functions, types, traits and impls, as listed in the index
+    pub items: usize,
+    /// `unsafe { ... }` blocks
+    pub unsafe_blocks: usize,
+}
+
+impl FileStats {
+    /// Statistics of a text file; items and unsafe blocks are only counted for Rust files
+    pub fn new(content: &str, is_rust: bool) -> Self {
+        let mut stats = Self::default();
+        let mut state = LexState::default();
+        for line in content.lines() {
+            stats.lines += 1;
+            match state.classify(line) {
+                LineKind::Blank => stats.blank += 1,
+                LineKind::Comment => stats.comments += 1,
+                LineKind::Code => stats.code += 1,
+            }
+        }
+        if is_rust {
+            stats.items = index_items(content).map_or(0, |items| items.len());
+            stats.unsafe_blocks = content.parse::<TokenStream>().map_or(0, count_unsafe_blocks);
+        }
+        stats
+    }
+
+    pub fn add(&mut self, other: &FileStats) {
+        self.lines += other.lines;
+        self.code += other.code;
+        self.comments += other.comments;
+        self.blank += other.blank;
+        self.items += other.items;
+        self.unsafe_blocks += other.unsafe_blocks;
+    }
+}
+
+/// A file's statistics and its place in the printout
+#[derive(Debug, Clone, Serialize)]
+pub struct FileSummary {
+    /// Path relative to the crate root
+    pub path: String,
+    #[serde(flatten)]
+    pub stats: FileStats,
+    /// First page of the file in the PDF (1-based)
+    pub first_page: usize,
+    pub pages: usize,
+}
+
+/// Statistics of a printed crate, as written by `--json`
+#[derive(Debug, Clone, Serialize)]
+pub struct CrateSummary {
+    pub name: String,
+    pub version: String,
+    pub files: Vec<FileSummary>,
+    pub total: FileStats,
+}
+
+impl CrateSummary {
+    pub fn new(name: &str, version: &str, files: Vec<FileSummary>) -> Self {
+        let mut total = FileStats::default();
+        for file in &files {
+            total.add(&file.stats);
+        }
+        Self { name: name.to_string(), version: version.to_string(), files, total }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum LineKind {
+    Blank,
+    Comment,
+    Code,
+}
+
+/// Comment and string state carried from one line to the next
+#[derive(Default)]
+struct LexState {
+    /// Nesting depth of `/* */` comments
+    block_comment: usize,
+    /// Inside a string literal; `Some(n)` for a raw string closed by `"` and `n` hashes
+    string: Option<Option<usize>>,
+}
+
+impl LexState {
+    fn classify(&mut self, line: &str) -> LineKind {
+        if line.trim().is_empty() && self.block_comment == 0 && self.string.is_none() {
+            return LineKind::Blank;
+        }
+        let (mut code, mut comment) = (false, false);
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied();
+            if self.block_comment > 0 {
+                comment = true;
+                match (c, next) {
+                    ('*', Some('/')) => { self.block_comment -= 1; i += 1; }
+                    ('/', Some('*')) => { self.block_comment += 1; i += 1; }
+                    _ => {}
+                }
+            } else if let Some(raw) = self.string {
+                code = true;
+                match (c, raw) {
+                    ('\\', None) => i += 1,
+                    ('"', None) => self.string = None,
+                    ('"', Some(hashes)) if chars[i + 1..].iter().take(hashes).filter(|&&h| h == '#').count() == hashes => {
+                        self.string = None;
+                        i += hashes;
+                    }
+                    _ => {}
+                }
+            } else {
+                match (c, next) {
+                    ('/', Some('/')) => {
+                        comment = true;
+                        break;
+                    }
+                    ('/', Some('*')) => { self.block_comment = 1; comment = true; i += 1; }
+                    ('"', _) => { self.string = Some(None); code = true; }
+                    ('r', Some('"' | '#')) if starts_raw_string(&chars[..i]) => {
+                        let hashes = chars[i + 1..].iter().take_while(|&&h| h == '#').count();
+                        code = true;
+                        if chars.get(i + 1 + hashes) == Some(&'"') {
+                            self.string = Some(Some(hashes));
+                            i += hashes + 1;
+                        }
+                    }
+                    // Escaped character literals such as '\'' or '\\': skip the escaped
+                    // character before looking for the closing quote
+                    ('\'', Some('\\')) => {
+                        code = true;
+                        if let Some(p) = chars.get(i + 3..).and_then(|rest| rest.iter().position(|&q| q == '\'')) {
+                            i += p + 3;
+                        }
+                    }
+                    // Character literals such as '"' must not open a string
+                    ('\'', _) if chars.get(i + 2) == Some(&'\'') => { code = true; i += 2; }
+                    _ if !c.is_whitespace() => code = true,
+                    _ => {}
+                }
+            }
+            i += 1;
+        }
+        match (code, comment) {
+            (true, _) => LineKind::Code,
+            (false, true) => LineKind::Comment,
+            // Whitespace inside a block comment or string
+            (false, false) if self.block_comment > 0 => LineKind::Comment,
+            (false, false) => LineKind::Blank,
+        }
+    }
+}
+
+/// Whether an `r` after `before` starts a raw string literal: `r"`, `br"` or `cr"`,
+/// but not the end of an identifier such as `for"`
+fn starts_raw_string(before: &[char]) -> bool {
+    let ident: Vec<&char> = before.iter().rev().take_while(|c| c.is_alphanumeric() || **c == '_').collect();
+    matches!(ident.as_slice(), [] | ['b'] | ['c'])
+}
+
+fn count_unsafe_blocks(stream: TokenStream) -> usize {
+    let tokens: Vec<TokenTree> = stream.into_iter().collect();
+    tokens.iter()
+        .enumerate()
+        .map(|(i, token)| match token {
+            TokenTree::Group(group) => count_unsafe_blocks(group.stream()),
+            TokenTree::Ident(ident) if ident == "unsafe" => {
+                usize::from(matches!(tokens.get(i + 1), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace))
+            }
+            _ => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_stats() {
+        let source = r##"//! Module docs
+
+/// A function
+fn read(ptr: *const u8) -> u8 {
+    let s = "// not a comment"; // trailing
+    /* block
+       comment */
+    let q = ('"', '\'','"', '\\');
+    unsafe { *ptr }
+}
+
+unsafe fn raw() -> &'static str {
+    r#"/* still
+    a string */"#
+}
+"##;
+        let stats = FileStats::new(source, true);
+        assert_eq!(stats, FileStats {
+            lines: 15,
+            code: 9,
+            comments: 4,
+            blank: 2,
+            items: 2,
+            unsafe_blocks: 1,
+        });
+
+        let mut total = stats;
+        total.add(&FileStats::new("a\n\nb\n", false));
+        assert_eq!((total.lines, total.code, total.blank), (18, 11, 3));
+    }
+
+    #[test]
+    fn test_raw_string_prefixes() {
+        // Comment markers and stray quotes inside raw byte and C strings are not comments
+        let source = r###"let a = br##"/* "# "##;
+let b = cr"// not a comment";
+let c = br##"
+// "# still a string
+"##;
+// comment
+"###;
+        let stats = FileStats::new(source, false);
+        assert_eq!((stats.lines, stats.code, stats.comments, stats.blank), (6, 5, 1, 0));
+    }
+}
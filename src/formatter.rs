@@ -0,0 +1,88 @@
+//! Source formatting via rustfmt
+//!
+//! Files are formatted in memory by piping their content through
+//! `rustfmt --emit stdout`, so the repository on disk (and any cached clone)
+//! is never modified and no `rustfmt.toml` has to be written into the tree.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, bail};
+
+/// Format Rust source code of the crate at `crate_root` with the given maximum line width.
+///
+/// rustfmt runs in the crate directory, so it picks up the crate's `rustfmt.toml`
+/// (or the workspace's). Only the line width is overridden via `--config`, so the
+/// printout always uses the requested width.
+pub fn format_source(content: &str, max_width: u32, edition: &str, crate_root: &Path) -> Result<String> {
+    let mut child = Command::new("rustfmt")
+        .current_dir(crate_root)
+        .arg("--emit")
+        .arg("stdout")
+        .arg("--edition")
+        .arg(edition)
+        .arg("--config")
+        .arg(format!("max_width={}", max_width))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run rustfmt. Is rustfmt installed?")?;
+
+    // Write stdin from a separate thread so a large file can't deadlock
+    // against rustfmt filling up its stdout pipe.
+    let mut stdin = child.stdin.take().context("Failed to open rustfmt stdin")?;
+    let input = content.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let output = child.wait_with_output()
+        .context("Failed to wait for rustfmt")?;
+
+    writer.join()
+        .map_err(|_| anyhow::anyhow!("rustfmt stdin writer panicked"))?
+        .context("Failed to write to rustfmt stdin")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("rustfmt failed: {}", stderr.lines().next().unwrap_or("unknown error"));
+    }
+
+    String::from_utf8(output.stdout)
+        .context("rustfmt produced non-UTF-8 output")
+}
+
+/// Format Rust source code, falling back to the original content if rustfmt fails.
+///
+/// Returns the content to render and the formatting error, if any.
+pub fn format_or_original(content: &str, max_width: u32, edition: &str, crate_root: &Path) -> (String, Option<String>) {
+    match format_source(content, max_width, edition, crate_root) {
+        Ok(formatted) => (formatted, None),
+        Err(e) => (content.to_string(), Some(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_format_falls_back_to_original() {
+        let broken = "fn main( {\n";
+        let (content, error) = format_or_original(broken, 80, "2021", Path::new("."));
+        assert_eq!(content, broken);
+        assert!(error.is_some());
+    }
+    
+    #[test]
+    fn test_format_uses_crate_config() {
+        if Command::new("rustfmt").arg("--version").output().is_err() {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("rustfmt.toml"), "hard_tabs = true\n").unwrap();
+        let (content, error) = format_or_original("fn main() { let x = 1; }\n", 80, "2021", dir.path());
+        assert_eq!(error, None);
+        assert_eq!(content, "fn main() {\n\tlet x = 1;\n}\n");
+    }
+}
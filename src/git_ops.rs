@@ -1,23 +1,116 @@
 //! Git operations using gitoxide (gix)
 
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use anyhow::{Context, Result, bail};
+use gix::bstr::ByteSlice;
 
-/// Clone a repository or open it if it already exists
-pub fn clone_or_open_repo(url: &str, dest: &Path, verbose: bool) -> Result<()> {
+/// Options limiting how much of a remote repository is downloaded and checked out
+#[derive(Debug, Clone, Default)]
+pub struct CloneOptions {
+    /// Only fetch the last N commits of history
+    pub depth: Option<NonZeroU32>,
+    /// Only check out the directories of these crates (plus every Cargo.toml,
+    /// so crate discovery still sees the workspace layout)
+    pub sparse_crates: Option<Vec<String>>,
+    /// SSH private key for `git@` and `ssh://` URLs (default: ssh agent and ~/.ssh/config)
+    pub ssh_key: Option<PathBuf>,
+    /// Use an existing clone as is, without fetching
+    pub offline: bool,
+}
+
+/// Environment variables holding access tokens for HTTPS remotes, with the host they apply
+/// to (`None`: any host) and the username sent along with the token
+const TOKEN_VARS: &[(&str, Option<&str>, &str)] = &[
+    ("GIT2PDF_TOKEN", None, "x-access-token"),
+    ("GITHUB_TOKEN", Some("github.com"), "x-access-token"),
+    ("GITLAB_TOKEN", Some("gitlab.com"), "oauth2"),
+];
+
+/// Options for opening (and cloning into) a repository.
+///
+/// Like `gix::prepare_clone`, configuration of the git installation is honored so that
+/// credential helpers configured for `git` are used for HTTPS remotes.
+fn open_options(options: &CloneOptions) -> gix::open::Options {
+    use gix::sec::trust::DefaultForLevel;
+    let mut opts = gix::open::Options::default_for_level(gix::sec::Trust::Full);
+    opts.permissions.config.git_binary = true;
+    if let Some(ref key) = options.ssh_key {
+        opts = opts.config_overrides([format!(
+            "core.sshCommand=ssh -i '{}' -o IdentitiesOnly=yes",
+            key.display(),
+        )]);
+    }
+    opts
+}
+
+/// Account for an HTTPS remote from a token in the environment, if one applies to its host.
+/// Without one, the credential helpers from the git configuration are asked instead.
+fn env_credentials(url: &gix::Url) -> Option<gix::sec::identity::Account> {
+    if !matches!(url.scheme, gix::url::Scheme::Https | gix::url::Scheme::Http) {
+        return None;
+    }
+    let (username, password) = token_for_host(url.host(), |var| std::env::var(var).ok())?;
+    Some(gix::sec::identity::Account {
+        username: url.user().map(str::to_string).unwrap_or(username),
+        password,
+        oauth_refresh_token: None,
+    })
+}
+
+/// Pick the first token variable that is set and applies to `host`,
+/// returning the username and token
+fn token_for_host(host: Option<&str>, var: impl Fn(&str) -> Option<String>) -> Option<(String, String)> {
+    TOKEN_VARS.iter()
+        .filter(|(_, for_host, _)| for_host.is_none() || *for_host == host)
+        .find_map(|(name, _, username)| {
+            var(name)
+                .filter(|token| !token.is_empty())
+                .map(|token| (username.to_string(), token))
+        })
+}
+
+/// Answer credential requests with a fixed account
+fn answer_with_account(
+    account: &gix::sec::identity::Account,
+    action: gix::credentials::helper::Action,
+) -> gix::credentials::protocol::Result {
+    match action {
+        gix::credentials::helper::Action::Get(ctx) => Ok(Some(gix::credentials::protocol::Outcome {
+            identity: account.clone(),
+            next: ctx.into(),
+        })),
+        // Nothing to store or erase for a token from the environment
+        gix::credentials::helper::Action::Store(_) | gix::credentials::helper::Action::Erase(_) => Ok(None),
+    }
+}
+
+/// Whether `path` is a bare repository (a local mirror without a worktree)
+pub fn is_bare_repo(path: &Path) -> bool {
+    gix::open(path).map(|repo| repo.is_bare()).unwrap_or(false)
+}
+
+/// Clone a repository or open it if it already exists.
+///
+/// Returns whether the remote's default branch was checked out, which is only the
+/// case for a fresh, non-sparse clone. Otherwise the caller checks out a ref with
+/// [`checkout_ref`] or [`sparse_checkout`].
+pub fn clone_or_open_repo(url: &str, dest: &Path, options: &CloneOptions, verbose: bool) -> Result<bool> {
     if dest.exists() && dest.join(".git").exists() {
         if verbose {
             println!("Repository already exists at {}", dest.display());
         }
         
-        // Optionally fetch latest changes
-        if let Err(e) = fetch_repo(dest, verbose) {
-            if verbose {
-                println!("Warning: Could not fetch latest changes: {}", e);
+        // Fetch latest changes, falling back to the existing clone
+        if !options.offline {
+            if let Err(e) = fetch_repo(dest, options, verbose) {
+                eprintln!("Warning: Could not fetch latest changes, using the cached clone: {:#}", e);
             }
         }
         
-        return Ok(());
+        return Ok(false);
     }
 
     if dest.exists() {
@@ -26,53 +119,129 @@ pub fn clone_or_open_repo(url: &str, dest: &Path, verbose: bool) -> Result<()> {
     }
 
     if verbose {
-        println!("Cloning repository from {}...", url);
+        match options.depth {
+            Some(depth) => println!("Cloning repository from {} (depth {})...", url, depth),
+            None => println!("Cloning repository from {}...", url),
+        }
     }
 
     // Prepare clone using gix
-    let url = gix::url::parse(url.into())
-        .context("Failed to parse git URL")?;
-    
-    let mut prepare = gix::prepare_clone(url, dest)
-        .context("Failed to prepare clone")?;
+    let mut prepare = prepare_clone(url, dest, gix::create::Kind::WithWorktree, options)?;
     
-    // Perform the fetch
-    let (mut checkout, _outcome) = prepare
-        .fetch_then_checkout(gix::progress::Discard, &gix::interrupt::IS_INTERRUPTED)
-        .context("Failed to fetch repository")?;
+    if let Some(depth) = options.depth {
+        prepare = prepare.with_shallow(gix::remote::fetch::Shallow::DepthAtRemote(depth));
+    }
     
-    // Checkout the main worktree
-    let (_repo, _outcome) = checkout
-        .main_worktree(gix::progress::Discard, &gix::interrupt::IS_INTERRUPTED)
-        .context("Failed to checkout worktree")?;
+    let checked_out = options.sparse_crates.is_none();
+    if !checked_out {
+        // Fetch without checking out, the caller writes only the selected crates
+        let (_repo, _outcome) = prepare
+            .fetch_only(gix::progress::Discard, &gix::interrupt::IS_INTERRUPTED)
+            .context("Failed to fetch repository")?;
+    } else {
+        // Perform the fetch
+        let (mut checkout, _outcome) = prepare
+            .fetch_then_checkout(gix::progress::Discard, &gix::interrupt::IS_INTERRUPTED)
+            .context("Failed to fetch repository")?;
+        
+        // Checkout the main worktree
+        let (_repo, _outcome) = checkout
+            .main_worktree(gix::progress::Discard, &gix::interrupt::IS_INTERRUPTED)
+            .context("Failed to checkout worktree")?;
+    }
 
     if verbose {
         println!("Clone complete");
     }
 
-    Ok(())
+    Ok(checked_out)
+}
+
+/// Prepare a clone of `url` into `dest`, authenticating with a token from the
+/// environment if one applies
+fn prepare_clone(
+    url: &str,
+    dest: &Path,
+    kind: gix::create::Kind,
+    options: &CloneOptions,
+) -> Result<gix::clone::PrepareFetch> {
+    let url = gix::url::parse(url.into())
+        .context("Failed to parse git URL")?;
+    let account = env_credentials(&url);
+    
+    let mut prepare = gix::clone::PrepareFetch::new(
+        url,
+        dest,
+        kind,
+        gix::create::Options::default(),
+        open_options(options),
+    ).context("Failed to prepare clone")?;
+    
+    if let Some(account) = account {
+        prepare = prepare.configure_connection(move |connection| {
+            let account = account.clone();
+            connection.set_credentials(move |action| answer_with_account(&account, action));
+            Ok(())
+        });
+    }
+    
+    Ok(prepare)
 }
 
 /// Fetch the latest changes from the remote
-fn fetch_repo(repo_path: &Path, verbose: bool) -> Result<()> {
+fn fetch_repo(repo_path: &Path, options: &CloneOptions, verbose: bool) -> Result<()> {
     if verbose {
         println!("Fetching latest changes...");
     }
 
-    let repo = gix::open(repo_path)
+    let repo = gix::open_opts(repo_path, open_options(options))
         .context("Failed to open repository")?;
 
     let remote = repo.find_default_remote(gix::remote::Direction::Fetch)
         .context("No default remote found")?
         .context("Failed to find remote")?;
     
-    let _outcome = remote
+    let account = remote.url(gix::remote::Direction::Fetch).and_then(env_credentials);
+    let mut connection = remote
         .connect(gix::remote::Direction::Fetch)
-        .context("Failed to connect to remote")?
-        .prepare_fetch(gix::progress::Discard, Default::default())
-        .context("Failed to prepare fetch")?
+        .context("Failed to connect to remote")?;
+    if let Some(account) = account {
+        connection.set_credentials(move |action| answer_with_account(&account, action));
+    }
+    
+    // Also ask for HEAD, to learn the remote's default branch
+    let ref_map_options = gix::remote::ref_map::Options {
+        extra_refspecs: vec![gix::refspec::parse("HEAD".into(), gix::refspec::parse::Operation::Fetch)?.to_owned()],
+        ..Default::default()
+    };
+    let mut prepare = connection
+        .prepare_fetch(gix::progress::Discard, ref_map_options)
+        .context("Failed to prepare fetch")?;
+    
+    if let Some(depth) = options.depth {
+        prepare = prepare.with_shallow(gix::remote::fetch::Shallow::DepthAtRemote(depth));
+    }
+    
+    let remote_head = prepare.ref_map().remote_refs.iter().find_map(|r| match r {
+        gix::protocol::handshake::Ref::Symbolic { full_ref_name, target, .. } if full_ref_name == "HEAD" => {
+            Some(target.to_string())
+        }
+        _ => None,
+    });
+    
+    let _outcome = prepare
         .receive(gix::progress::Discard, &gix::interrupt::IS_INTERRUPTED)
         .context("Failed to fetch")?;
+    
+    // Like `git remote set-head`: remember the default branch for offline runs
+    if let Some(branch) = remote_head.as_deref().and_then(|t| t.strip_prefix("refs/heads/")) {
+        set_reference(
+            &repo,
+            "refs/remotes/origin/HEAD",
+            gix::refs::Target::Symbolic(format!("refs/remotes/origin/{}", branch).try_into()?),
+            "git2pdf: remote HEAD",
+        )?;
+    }
 
     if verbose {
         println!("Fetch complete");
@@ -81,31 +250,288 @@ fn fetch_repo(repo_path: &Path, verbose: bool) -> Result<()> {
     Ok(())
 }
 
-/// Checkout a specific branch, tag, or commit
-pub fn checkout_ref(repo_path: &Path, git_ref: &str, verbose: bool) -> Result<()> {
+/// Write only the directories of the crates selected by `crates` (`--crates` selectors,
+/// see [`CrateFilter`](crate::crate_filter::CrateFilter)) and all Cargo.toml files of
+/// `git_ref` into the worktree of the repository at `repo_path`, detaching HEAD there.
+///
+/// Other files are removed from the worktree and marked skip-worktree in the index, like
+/// in a git sparse checkout. Branches resolve like in [`checkout_ref`].
+pub fn sparse_checkout(repo_path: &Path, git_ref: &str, crates: &[String], verbose: bool) -> Result<()> {
     let repo = gix::open(repo_path)
         .context("Failed to open repository")?;
-
-    // Try to find the reference
-    let reference = find_reference(&repo, git_ref)?;
+    
+    let filter = crate::crate_filter::CrateFilter::from_selectors(crates.iter().map(String::as_str))?;
+    let commit_id = resolve_checkout_commit(&repo, git_ref)?;
+    let records = tree_records(&repo, commit_id)?;
+    
+    // Find the directories of the selected crates by reading their manifests
+    let mut crate_dirs: Vec<String> = Vec::new();
+    for entry in &records {
+        let path = entry.filepath.to_str_lossy();
+        if !entry.mode.is_blob() || !(path == "Cargo.toml" || path.ends_with("/Cargo.toml")) {
+            continue;
+        }
+        let blob = repo.find_blob(entry.oid)?;
+        let name = crate::crate_discovery::package_name(&String::from_utf8_lossy(&blob.data));
+        let dir = path.trim_end_matches("Cargo.toml");
+        if name.is_some_and(|n| filter.matches(&n, dir.trim_end_matches('/'))) {
+            crate_dirs.push(dir.to_string());
+        }
+    }
+    
+    if crate_dirs.is_empty() {
+        bail!("None of the crates {} found in {}", crates.join(", "), git_ref);
+    }
+    
+    clear_worktree(repo_path)?;
+    let file_count = checkout_tree(&repo, commit_id, |path| {
+        path == "Cargo.toml"
+            || path.ends_with("/Cargo.toml")
+            || crate_dirs.iter().any(|dir| path.starts_with(dir.as_str()))
+    })?;
+    set_reference(&repo, "HEAD", gix::refs::Target::Object(commit_id), &format!("git2pdf: sparse checkout {}", git_ref))?;
     
     if verbose {
-        println!("Found reference: {}", git_ref);
+        println!("Sparse checkout of {} ({} files): {}", git_ref, file_count,
+            crate_dirs.iter().map(|d| if d.is_empty() { "." } else { d.as_str() }).collect::<Vec<_>>().join(", "));
+    }
+    
+    Ok(())
+}
+
+/// All entries of the tree of a commit, recursively
+fn tree_records(repo: &gix::Repository, commit_id: gix::ObjectId) -> Result<Vec<gix::traverse::tree::recorder::Entry>> {
+    let tree = repo.find_commit(commit_id)?
+        .tree()
+        .context("Failed to read commit tree")?;
+    
+    let mut recorder = gix::traverse::tree::Recorder::default();
+    tree.traverse()
+        .breadthfirst(&mut recorder)
+        .with_context(|| format!("Failed to traverse tree of {}", commit_id))?;
+    
+    Ok(recorder.records)
+}
+
+/// Check out the tree of `commit_id` into the cleared worktree of `repo` and write the
+/// matching index, so status checks afterwards see a clean worktree. Symlinks and the
+/// executable bit are restored like by `git checkout`. Paths not matching `keep` are
+/// marked skip-worktree instead of being written. Returns the number of files written.
+fn checkout_tree(repo: &gix::Repository, commit_id: gix::ObjectId, keep: impl Fn(&str) -> bool) -> Result<usize> {
+    let workdir = repo.workdir().context("Repository has no worktree")?;
+    let tree_id = repo.find_commit(commit_id)?.tree_id()?;
+    let mut index = repo.index_from_tree(&tree_id)
+        .with_context(|| format!("Failed to read tree of {}", commit_id))?;
+    for (entry, path) in index.entries_mut_with_paths() {
+        if !keep(&path.to_str_lossy()) {
+            // Skip-worktree is an extended flag, only stored with the EXTENDED bit
+            entry.flags.insert(gix::index::entry::Flags::SKIP_WORKTREE | gix::index::entry::Flags::EXTENDED);
+        }
     }
     
-    // Get the commit id - peel to the actual commit
-    let commit_id = reference.id().detach();
+    let mut options = repo.checkout_options(gix::worktree::stack::state::attributes::Source::IdMapping)?;
+    options.destination_is_initially_empty = true;
+    let outcome = gix::worktree::state::checkout(
+        &mut index,
+        workdir,
+        repo.objects.clone().into_arc()?,
+        &gix::progress::Discard,
+        &gix::progress::Discard,
+        &gix::interrupt::IS_INTERRUPTED,
+        options,
+    ).context("Failed to check out files")?;
+    // Only on case-insensitive file systems: one of the files is missing
+    for collision in &outcome.collisions {
+        eprintln!("Warning: {} was not checked out, it collides with another file", collision.path);
+    }
     
-    // Update HEAD to point to this commit
-    let head_ref = repo.find_reference("HEAD").ok();
+    index.write(Default::default())
+        .context("Failed to write the index")?;
+    Ok(outcome.files_updated)
+}
+
+/// Write the regular files among `records` whose path matches `filter` into `dest`,
+/// returning the number of files written
+fn write_records(
+    repo: &gix::Repository,
+    records: &[gix::traverse::tree::recorder::Entry],
+    dest: &Path,
+    filter: impl Fn(&str) -> bool,
+) -> Result<usize> {
+    let mut file_count = 0;
+    for entry in records {
+        // Only regular files: skip trees (created on demand), symlinks and submodules
+        if !entry.mode.is_blob() || !filter(&entry.filepath.to_str_lossy()) {
+            continue;
+        }
+        let path = dest.join(gix::path::from_bstr(entry.filepath.as_bstr()));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let blob = repo.find_blob(entry.oid)?;
+        std::fs::write(&path, &blob.data)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        file_count += 1;
+    }
+    Ok(file_count)
+}
+
+/// Check out a branch, tag, or commit into the worktree of a clone and detach HEAD there.
+///
+/// Branches resolve to their remote-tracking ref, so a cached clone gets the commit that
+/// was just fetched rather than the one it was cloned at. Returns the short commit hash.
+pub fn checkout_ref(repo_path: &Path, git_ref: &str, verbose: bool) -> Result<String> {
+    let repo = gix::open(repo_path)
+        .context("Failed to open repository")?;
+
+    let commit_id = resolve_checkout_commit(&repo, git_ref)?;
     
     if verbose {
-        println!("Checked out {} ({})", git_ref, commit_id);
+        println!("Found reference: {} ({})", git_ref, commit_id);
     }
     
+    clear_worktree(repo_path)?;
+    let file_count = checkout_tree(&repo, commit_id, |_| true)?;
+    set_reference(&repo, "HEAD", gix::refs::Target::Object(commit_id), &format!("git2pdf: checkout {}", git_ref))?;
+    
+    if verbose {
+        println!("Checked out {} ({}, {} files)", git_ref, commit_id, file_count);
+    }
+    
+    Ok(commit_id.to_hex_with_len(8).to_string())
+}
+
+/// Resolve a ref to check out, preferring the remote-tracking branch of the same name
+fn resolve_checkout_commit(repo: &gix::Repository, git_ref: &str) -> Result<gix::ObjectId> {
+    let remote_branch = format!("refs/remotes/origin/{}", git_ref);
+    if repo.find_reference(remote_branch.as_str()).is_ok() {
+        resolve_commit(repo, &remote_branch)
+    } else {
+        resolve_commit(repo, git_ref)
+    }
+}
+
+/// Point a reference at an object or another reference, creating it if needed
+fn set_reference(repo: &gix::Repository, name: &str, target: gix::refs::Target, message: &str) -> Result<()> {
+    use gix::refs::transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog};
+    repo.edit_reference(RefEdit {
+        change: Change::Update {
+            log: LogChange {
+                mode: RefLog::AndReference,
+                force_create_reflog: false,
+                message: message.into(),
+            },
+            expected: PreviousValue::Any,
+            new: target,
+        },
+        name: name.try_into()?,
+        deref: false,
+    }).with_context(|| format!("Failed to update {}", name))?;
     Ok(())
 }
 
+/// Remove everything but the .git directory from a worktree
+fn clear_worktree(repo_path: &Path) -> Result<()> {
+    for entry in std::fs::read_dir(repo_path)? {
+        let entry = entry?;
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            std::fs::remove_dir_all(entry.path())?;
+        } else {
+            std::fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// The default branch: the remote's HEAD as recorded by the last fetch, else the
+/// branch checked out when cloning, else `main` or `master`
+pub fn default_branch(repo_path: &Path) -> Result<String> {
+    let repo = gix::discover(repo_path)
+        .context("Failed to open repository")?;
+    
+    if let Ok(reference) = repo.find_reference("refs/remotes/origin/HEAD") {
+        if let Some(target) = reference.target().try_name() {
+            if let Some(branch) = target.as_bstr().to_str_lossy().strip_prefix("refs/remotes/origin/") {
+                return Ok(branch.to_string());
+            }
+        }
+    }
+    
+    if let Some(head) = repo.head_name()? {
+        if let Some(branch) = head.as_bstr().to_str_lossy().strip_prefix("refs/heads/") {
+            return Ok(branch.to_string());
+        }
+    }
+    
+    for name in ["main", "master"] {
+        if find_reference(&repo, name).is_ok() {
+            return Ok(name.to_string());
+        }
+    }
+    
+    bail!("Could not determine the default branch, use --ref")
+}
+
+/// Branches and tags of a repository, for `--list-refs`
+#[derive(Debug, Clone, Default)]
+pub struct RefListing {
+    pub default_branch: Option<String>,
+    /// Branch name and short commit hash (local and remote-tracking branches, merged)
+    pub branches: Vec<(String, String)>,
+    /// Tag name and short commit hash
+    pub tags: Vec<(String, String)>,
+}
+
+/// List the branches and tags of a repository
+pub fn list_refs(repo_path: &Path) -> Result<RefListing> {
+    let repo = gix::discover(repo_path)
+        .context("Failed to open repository")?;
+    let references = repo.references()?;
+    
+    let short_id = |reference: gix::Reference<'_>| -> Option<String> {
+        reference.into_fully_peeled_id().ok().map(|id| id.to_hex_with_len(8).to_string())
+    };
+    
+    // Remote-tracking branches are inserted last, so they win over stale local branches
+    let mut branches: BTreeMap<String, String> = BTreeMap::new();
+    for reference in references.local_branches()?.flatten() {
+        let name = reference.name().shorten().to_string();
+        if let Some(id) = short_id(reference) {
+            branches.insert(name, id);
+        }
+    }
+    for reference in references.remote_branches()?.flatten() {
+        let name = reference.name().shorten().to_string();
+        let Some(name) = name.strip_prefix("origin/").map(str::to_string) else {
+            continue;
+        };
+        if name == "HEAD" {
+            continue;
+        }
+        if let Some(id) = short_id(reference) {
+            branches.insert(name, id);
+        }
+    }
+    
+    let mut tags = Vec::new();
+    for reference in references.tags()?.flatten() {
+        let name = reference.name().shorten().to_string();
+        if let Some(id) = short_id(reference) {
+            tags.push((name, id));
+        }
+    }
+    tags.sort();
+    
+    Ok(RefListing {
+        default_branch: default_branch(repo_path).ok(),
+        branches: branches.into_iter().collect(),
+        tags,
+    })
+}
+
 /// Find a reference by name (branch, tag, or commit)
 fn find_reference<'a>(repo: &'a gix::Repository, name: &str) -> Result<gix::Reference<'a>> {
     // Try as a local branch first
@@ -134,26 +560,252 @@ fn find_reference<'a>(repo: &'a gix::Repository, name: &str) -> Result<gix::Refe
     bail!("Could not find reference: {}", name)
 }
 
-/// Try to checkout main or master branch
-#[allow(dead_code)]
-pub fn checkout_default_branch(repo_path: &Path, verbose: bool) -> Result<String> {
-    let repo = gix::open(repo_path)
+/// Write the files of `git_ref` into `dest` (without a .git directory),
+/// returning the short commit hash of the exported commit.
+///
+/// This leaves the repository's own worktree and HEAD untouched, so several
+/// refs can be exported side by side.
+pub fn export_ref(repo_path: &Path, git_ref: &str, dest: &Path, verbose: bool) -> Result<Export> {
+    let repo = gix::discover(repo_path)
+        .context("Failed to open repository")?;
+    
+    let commit_id = resolve_commit(&repo, git_ref)?;
+    let records = tree_records(&repo, commit_id)?;
+    
+    if dest.exists() {
+        std::fs::remove_dir_all(dest)
+            .context("Failed to remove existing export directory")?;
+    }
+    std::fs::create_dir_all(dest)?;
+    
+    let file_count = write_records(&repo, &records, dest, |_| true)?;
+    
+    if verbose {
+        println!("Exported {} files of {} ({})", file_count, git_ref, commit_id);
+    }
+    
+    let path = |entry: &gix::traverse::tree::recorder::Entry| entry.filepath.to_str_lossy().into_owned();
+    Ok(Export {
+        hash: commit_id.to_hex_with_len(8).to_string(),
+        symlinks: records.iter().filter(|e| e.mode.is_link()).map(path).collect(),
+        gitlinks: records.iter().filter(|e| e.mode.is_commit()).map(|e| (path(e), e.oid)).collect(),
+    })
+}
+
+/// A ref exported by [`export_ref`]
+#[derive(Debug, Clone)]
+pub struct Export {
+    /// Short commit hash of the exported commit
+    pub hash: String,
+    /// Symlinks in the tree, which are not exported (repository-relative paths)
+    pub symlinks: Vec<String>,
+    /// Submodule commits recorded in the tree, by path
+    gitlinks: HashMap<String, gix::ObjectId>,
+}
+
+impl Export {
+    /// The submodules present at the exported ref, with the commits recorded there
+    /// instead of the ones recorded in HEAD
+    pub fn submodules(&self, submodules: &[SubmoduleInfo]) -> Vec<SubmoduleInfo> {
+        submodules.iter()
+            .filter_map(|submodule| self.gitlinks.get(&submodule.path).map(|&commit| SubmoduleInfo {
+                commit: Some(commit),
+                ..submodule.clone()
+            }))
+            .collect()
+    }
+}
+
+/// A submodule registered in the repository's .gitmodules
+#[derive(Debug, Clone)]
+pub struct SubmoduleInfo {
+    /// Path relative to the repository root
+    pub path: String,
+    /// URL to fetch from, with relative URLs resolved against the superproject's remote
+    pub url: Option<String>,
+    /// Commit recorded in the superproject's HEAD
+    pub commit: Option<gix::ObjectId>,
+}
+
+/// List the submodules of a repository (empty if it has none)
+pub fn list_submodules(repo_path: &Path) -> Result<Vec<SubmoduleInfo>> {
+    let repo = gix::discover(repo_path)
+        .context("Failed to open repository")?;
+    
+    let Some(submodules) = repo.submodules().context("Failed to read .gitmodules")? else {
+        return Ok(Vec::new());
+    };
+    
+    let origin = repo.find_default_remote(gix::remote::Direction::Fetch)
+        .and_then(|r| r.ok())
+        .and_then(|r| r.url(gix::remote::Direction::Fetch).map(|u| u.to_bstring().to_string()));
+    
+    let mut result = Vec::new();
+    for submodule in submodules {
+        let path = submodule.path()?.to_string();
+        let url = submodule.url().ok().map(|u| {
+            let url = u.to_bstring().to_string();
+            match origin {
+                Some(ref base) if url.starts_with("./") || url.starts_with("../") => resolve_relative_url(base, &url),
+                _ => url,
+            }
+        });
+        result.push(SubmoduleInfo {
+            path,
+            url,
+            commit: submodule.head_id().ok().flatten(),
+        });
+    }
+    
+    Ok(result)
+}
+
+/// Resolve a relative submodule URL like "../other.git" against the superproject's URL
+fn resolve_relative_url(base: &str, relative: &str) -> String {
+    let mut base = base.trim_end_matches('/').to_string();
+    let mut rest = relative;
+    loop {
+        if let Some(r) = rest.strip_prefix("./") {
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("../") {
+            rest = r;
+            // "git@host:owner/repo" has ':' as the first separator
+            match base.rfind(['/', ':']) {
+                Some(idx) => base.truncate(idx + usize::from(base.as_bytes()[idx] == b':')),
+                None => base.clear(),
+            }
+            base = base.trim_end_matches('/').to_string();
+        } else {
+            break;
+        }
+    }
+    if base.ends_with(':') {
+        format!("{}{}", base, rest)
+    } else {
+        format!("{}/{}", base, rest)
+    }
+}
+
+/// Fetch a submodule into `mirror` (a bare clone, reused across runs) and write the
+/// files of its recorded commit into `dest`
+pub fn fetch_submodule(
+    submodule: &SubmoduleInfo,
+    dest: &Path,
+    mirror: &Path,
+    options: &CloneOptions,
+    verbose: bool,
+) -> Result<()> {
+    let url = submodule.url.as_deref()
+        .context("Submodule has no URL")?;
+    let commit = submodule.commit
+        .context("Submodule commit is not recorded in HEAD")?;
+    
+    if verbose {
+        println!("Fetching submodule {} from {}...", submodule.path, url);
+    }
+    
+    // The recorded commit may be anywhere in the history, so never fetch shallow
+    let options = CloneOptions { depth: None, ..options.clone() };
+    if mirror.exists() {
+        fetch_repo(mirror, &options, verbose)?;
+    } else {
+        let mut prepare = prepare_clone(url, mirror, gix::create::Kind::Bare, &options)?;
+        prepare.fetch_only(gix::progress::Discard, &gix::interrupt::IS_INTERRUPTED)
+            .context("Failed to fetch submodule")?;
+    }
+    
+    let repo = gix::open(mirror)
+        .context("Failed to open submodule repository")?;
+    let records = tree_records(&repo, commit)?;
+    std::fs::create_dir_all(dest)?;
+    let file_count = write_records(&repo, &records, dest, |_| true)?;
+    
+    if verbose {
+        println!("Checked out submodule {} at {} ({} files)", submodule.path, commit.to_hex_with_len(8), file_count);
+    }
+    
+    Ok(())
+}
+
+/// Uncommitted changes in a repository, relative to HEAD
+#[derive(Debug, Clone, Default)]
+pub struct WorktreeStatus {
+    /// Files whose staged (index) version differs from HEAD
+    pub staged: Vec<String>,
+    /// Files whose working tree version differs from the index, including untracked files
+    pub unstaged: Vec<String>,
+}
+
+impl WorktreeStatus {
+    /// Files that differ from HEAD in the working tree (staged or not), sorted
+    pub fn worktree_changes(&self) -> Vec<String> {
+        let mut files: Vec<String> = self.staged.iter().chain(&self.unstaged).cloned().collect();
+        files.sort();
+        files.dedup();
+        files
+    }
+}
+
+/// Compare HEAD, the index and the working tree of a repository (ignored files excluded)
+pub fn worktree_status(repo_path: &Path) -> Result<WorktreeStatus> {
+    let repo = gix::discover(repo_path)
         .context("Failed to open repository")?;
     
-    // Try 'main' first
-    if find_reference(&repo, "main").is_ok() {
-        checkout_ref(repo_path, "main", verbose)?;
-        return Ok("main".to_string());
+    let mut status = WorktreeStatus::default();
+    let items = repo.status(gix::progress::Discard)
+        .context("Failed to prepare status")?
+        .untracked_files(gix::status::UntrackedFiles::Files)
+        .index_worktree_submodules(None)
+        .into_iter(None)
+        .context("Failed to compute status")?;
+    for item in items {
+        let item = item.context("Failed to compute status")?;
+        let path = item.location().to_str_lossy().into_owned();
+        match item {
+            gix::status::Item::TreeIndex(_) => status.staged.push(path),
+            gix::status::Item::IndexWorktree(_) => status.unstaged.push(path),
+        }
+    }
+    status.staged.sort();
+    status.unstaged.sort();
+    
+    Ok(status)
+}
+
+/// Write the staged (index) version of every file into `dest`
+pub fn export_index(repo_path: &Path, dest: &Path, verbose: bool) -> Result<()> {
+    let repo = gix::discover(repo_path)
+        .context("Failed to open repository")?;
+    let index = repo.index_or_empty()
+        .context("Failed to read index")?;
+    
+    if dest.exists() {
+        std::fs::remove_dir_all(dest)
+            .context("Failed to remove existing export directory")?;
+    }
+    std::fs::create_dir_all(dest)?;
+    
+    let mut file_count = 0;
+    for entry in index.entries() {
+        // Only regular files: skip symlinks and submodules
+        if !matches!(entry.mode, gix::index::entry::Mode::FILE | gix::index::entry::Mode::FILE_EXECUTABLE) {
+            continue;
+        }
+        let path = dest.join(gix::path::from_bstr(entry.path(&index)));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let blob = repo.find_blob(entry.id)?;
+        std::fs::write(&path, &blob.data)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        file_count += 1;
     }
     
-    // Try 'master'
-    if find_reference(&repo, "master").is_ok() {
-        checkout_ref(repo_path, "master", verbose)?;
-        return Ok("master".to_string());
+    if verbose {
+        println!("Exported {} staged files", file_count);
     }
     
-    // Use HEAD
-    Ok("HEAD".to_string())
+    Ok(())
 }
 
 /// Get the current HEAD commit hash (short form)
@@ -170,3 +822,495 @@ pub fn get_git_hash(repo_path: &Path) -> Result<String> {
     // Return first 8 characters of the hash
     Ok(commit_id.to_string().chars().take(8).collect())
 }
+
+/// Blame information for a single line
+#[derive(Debug, Clone)]
+pub struct BlameLine {
+    /// Short hash of the commit that last changed the line
+    pub short_hash: String,
+    /// Initials of the commit author (e.g. "FS")
+    pub author_initials: String,
+    /// Age of the commit in days
+    pub age_days: i64,
+}
+
+/// A repository opened once for blame, shared by the rendering threads
+pub struct BlameRepo {
+    repo: gix::ThreadSafeRepository,
+    /// Commit the printed files come from
+    commit: gix::ObjectId,
+}
+
+/// Open the repository containing `repo_path` to blame files at `git_ref`, or at HEAD
+pub fn open_blame_repo(repo_path: &Path, git_ref: Option<&str>) -> Result<BlameRepo> {
+    let repo = gix::discover(repo_path)
+        .context("Failed to open repository")?;
+    let commit = match git_ref {
+        Some(git_ref) => resolve_commit(&repo, git_ref)?,
+        None => repo.head_id()
+            .context("HEAD has no commit id (empty repository?)")?
+            .detach(),
+    };
+    Ok(BlameRepo { repo: repo.into_sync(), commit })
+}
+
+/// Blame a file at the commit of `repo`, returning one entry per line of the committed file.
+///
+/// `file_path` is relative to `repo_path`, which may be a subdirectory of the repository.
+/// Files with uncommitted changes don't match the commit; callers should not blame them.
+pub fn blame_file(repo: &BlameRepo, repo_path: &Path, file_path: &Path) -> Result<Vec<BlameLine>> {
+    let commit = repo.commit;
+    let repo = repo.repo.to_thread_local();
+    
+    let repo_rel_path = repo_relative_path(&repo, repo_path, file_path)?;
+    
+    let outcome = repo.blame_file(repo_rel_path.as_str().into(), commit, Default::default())
+        .with_context(|| format!("Failed to blame {}", repo_rel_path))?;
+    
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    
+    // Look up each commit only once
+    let mut commits: HashMap<gix::ObjectId, BlameLine> = HashMap::new();
+    let mut lines = Vec::new();
+    
+    for entry in &outcome.entries {
+        let info = match commits.get(&entry.commit_id) {
+            Some(info) => info.clone(),
+            None => {
+                let commit = repo.find_commit(entry.commit_id)
+                    .context("Failed to find blamed commit")?;
+                let author_initials = commit.author()
+                    .map(|a| initials(&a.name.to_string()))
+                    .unwrap_or_default();
+                let age_days = commit.time()
+                    .map(|t| (now - t.seconds).max(0) / 86_400)
+                    .unwrap_or(0);
+                let info = BlameLine {
+                    short_hash: entry.commit_id.to_hex_with_len(7).to_string(),
+                    author_initials,
+                    age_days,
+                };
+                commits.insert(entry.commit_id, info.clone());
+                info
+            }
+        };
+        
+        let start = entry.start_in_blamed_file as usize;
+        let end = start + entry.len.get() as usize;
+        if lines.len() < end {
+            lines.resize(end, None);
+        }
+        for line in &mut lines[start..end] {
+            *line = Some(info.clone());
+        }
+    }
+    
+    Ok(lines.into_iter()
+        .map(|l| l.unwrap_or_else(|| BlameLine {
+            short_hash: String::new(),
+            author_initials: String::new(),
+            age_days: 0,
+        }))
+        .collect())
+}
+
+/// Path of `file_path` (relative to `repo_path`) relative to the repository root,
+/// with forward slashes as used in git trees
+fn repo_relative_path(repo: &gix::Repository, repo_path: &Path, file_path: &Path) -> Result<String> {
+    let workdir = repo.workdir()
+        .context("Repository has no working directory")?;
+    
+    let prefix = repo_path.canonicalize()?
+        .strip_prefix(workdir.canonicalize()?)
+        .map(|p| p.to_path_buf())
+        .unwrap_or_default();
+    
+    Ok(prefix.join(file_path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+/// Resolve a branch, tag or remote branch, or any revision git understands (`abc1234`,
+/// `HEAD~5`, `v1.0^{commit}`), to a commit id
+fn resolve_commit(repo: &gix::Repository, name: &str) -> Result<gix::ObjectId> {
+    // Branches of a clone may only exist as remote-tracking refs, which revision
+    // parsing doesn't find by their short name
+    let id = match find_reference(repo, name) {
+        Ok(reference) => reference.into_fully_peeled_id()
+            .with_context(|| format!("Failed to resolve reference: {}", name))?
+            .detach(),
+        Err(_) => repo.rev_parse_single(name)
+            .with_context(|| format!("Could not find reference or commit: {}", name))?
+            .detach(),
+    };
+    // Annotated tags point to a tag object, peel through to the commit
+    let commit = repo.find_object(id)?
+        .peel_to_commit()
+        .with_context(|| format!("{} does not point to a commit", name))?;
+    Ok(commit.id)
+}
+
+/// How a rendered line changed relative to a baseline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineChange {
+    /// Line is identical to the baseline
+    Unchanged,
+    /// Line was added since the baseline
+    Added,
+    /// Line replaces one or more baseline lines
+    Modified,
+    /// Unchanged line, but baseline lines directly above it were removed
+    DeletedAbove,
+    /// Unchanged last line, but baseline lines after it were removed
+    DeletedBelow,
+}
+
+/// Files changed between a baseline revision and the printed version
+#[derive(Debug, Default)]
+pub struct BaselineChanges {
+    /// Repository-relative path -> file content at the baseline (`None` if added since)
+    files: HashMap<String, Option<String>>,
+    /// Repository root prefix of the directory the changes were computed for
+    repo_path: std::path::PathBuf,
+}
+
+impl BaselineChanges {
+    /// Look up a file (relative to the path passed to [`changes_since`]).
+    ///
+    /// Returns `None` if the file is unchanged, `Some(None)` if it was added
+    /// and `Some(Some(content))` with the baseline content if it was modified.
+    pub fn get(&self, file_path: &Path) -> Option<Option<&str>> {
+        let path = self.repo_path.join(file_path)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+        self.files.get(&path).map(|c| c.as_deref())
+    }
+}
+
+/// Collect the files that differ between `base` and the printed version, together with
+/// their content at `base`.
+///
+/// The printed version is the commit `head` (HEAD if `None`) plus `local_changes`: the
+/// repository-relative paths of files edited in the working tree or index, which are
+/// compared against `base` as well.
+pub fn changes_since(repo_path: &Path, base: &str, head: Option<&str>, local_changes: &[String]) -> Result<BaselineChanges> {
+    use gix::object::tree::diff::ChangeDetached;
+    
+    let repo = gix::discover(repo_path)
+        .context("Failed to open repository")?;
+    
+    let base_tree = repo.find_commit(resolve_commit(&repo, base)?)?
+        .tree()
+        .context("Failed to read baseline tree")?;
+    let head_commit = match head {
+        Some(head) => repo.find_commit(resolve_commit(&repo, head)?)?,
+        None => repo.head_commit().context("Failed to get HEAD commit")?,
+    };
+    let head_tree = head_commit.tree()
+        .context("Failed to read HEAD tree")?;
+    
+    let changes = repo.diff_tree_to_tree(Some(&base_tree), Some(&head_tree), None)
+        .with_context(|| format!("Failed to diff {} against {}", base, head.unwrap_or("HEAD")))?;
+    
+    let blob_text = |id: gix::ObjectId| -> Result<String> {
+        let blob = repo.find_blob(id)?;
+        Ok(String::from_utf8_lossy(&blob.data).into_owned())
+    };
+    
+    let mut files = HashMap::new();
+    for change in changes {
+        match change {
+            ChangeDetached::Addition { location, entry_mode, .. } if entry_mode.is_blob() => {
+                files.insert(location.to_string(), None);
+            }
+            ChangeDetached::Modification { location, previous_id, entry_mode, .. } if entry_mode.is_blob() => {
+                files.insert(location.to_string(), Some(blob_text(previous_id)?));
+            }
+            ChangeDetached::Rewrite { location, source_id, entry_mode, .. } if entry_mode.is_blob() => {
+                files.insert(location.to_string(), Some(blob_text(source_id)?));
+            }
+            _ => {}
+        }
+    }
+    
+    // Files edited locally may be unchanged between the commits, but not in the printout
+    for path in local_changes {
+        if files.contains_key(path) {
+            continue;
+        }
+        let content = match base_tree.lookup_entry_by_path(path)
+            .with_context(|| format!("Failed to look up {} in {}", path, base))?
+        {
+            Some(entry) if entry.mode().is_blob() => Some(blob_text(entry.object_id())?),
+            Some(_) => continue,
+            None => None,
+        };
+        files.insert(path.clone(), content);
+    }
+    
+    let prefix = repo_relative_path(&repo, repo_path, Path::new(""))?;
+    
+    Ok(BaselineChanges {
+        files,
+        repo_path: std::path::PathBuf::from(prefix),
+    })
+}
+
+/// Compute per-line changes of `after` relative to `before` (one entry per line of `after`)
+pub fn line_changes(before: &str, after: &str) -> Vec<LineChange> {
+    use gix::diff::blob::{diff, intern::InternedInput, Algorithm};
+    
+    let mut changes = vec![LineChange::Unchanged; after.lines().count()];
+    let input = InternedInput::new(before, after);
+    
+    diff(Algorithm::Histogram, &input, |removed: std::ops::Range<u32>, added: std::ops::Range<u32>| {
+        if added.is_empty() {
+            match changes.get_mut(added.start as usize) {
+                Some(line) => *line = LineChange::DeletedAbove,
+                // Removed from the end of the file: mark the last line that is left
+                None => if let Some(last @ LineChange::Unchanged) = changes.last_mut() {
+                    *last = LineChange::DeletedBelow;
+                },
+            }
+            return;
+        }
+        let kind = if removed.is_empty() { LineChange::Added } else { LineChange::Modified };
+        for line in added.start..added.end {
+            if let Some(change) = changes.get_mut(line as usize) {
+                *change = kind;
+            }
+        }
+    });
+    
+    changes
+}
+
+/// Summary of a single commit for the commit log appendix
+#[derive(Debug, Clone)]
+pub struct CommitSummary {
+    /// Short commit hash
+    pub short_hash: String,
+    /// Author name
+    pub author: String,
+    /// Commit date (YYYY-MM-DD)
+    pub date: String,
+    /// First line of the commit message
+    pub subject: String,
+    /// Repository-relative paths of the files touched by the commit
+    pub files: Vec<String>,
+}
+
+impl CommitSummary {
+    /// Files touched below `prefix` (a repository-relative directory, empty for the root)
+    pub fn files_under<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a String> + 'a {
+        self.files.iter().filter(move |f| {
+            prefix.is_empty() || f.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+}
+
+/// List the commits reachable from `to` but not from `from` (`git log from..to`), newest
+/// first, with the files each commit touched (compared to its first parent)
+pub fn commit_log(repo_path: &Path, from: &str, to: &str) -> Result<Vec<CommitSummary>> {
+    let repo = gix::discover(repo_path)
+        .context("Failed to open repository")?;
+    
+    let from_id = resolve_commit(&repo, from)?;
+    let to_id = if to == "HEAD" {
+        repo.head_id().context("HEAD has no commit id (empty repository?)")?.detach()
+    } else {
+        resolve_commit(&repo, to)?
+    };
+    
+    // Hide `from` with all its ancestors: a boundary would only stop at `from` itself, and
+    // commits of merged branches would lead back into the history before it
+    let walk = repo.rev_walk([to_id])
+        .with_hidden([from_id])
+        .all()
+        .context("Failed to walk commit history")?;
+    
+    let mut commits = Vec::new();
+    for info in walk {
+        let info = info.context("Failed to traverse commit")?;
+        let commit = info.object()?;
+        let author = commit.author()
+            .map(|a| a.name.to_string())
+            .unwrap_or_default();
+        let date = commit.time()
+            .map(|t| t.format(gix::date::time::format::SHORT))
+            .unwrap_or_default();
+        let subject = commit.message()
+            .map(|m| m.summary().to_string())
+            .unwrap_or_default();
+        
+        // Files touched relative to the first parent (or everything for a root commit)
+        let tree = commit.tree()?;
+        let parent_tree = match info.parent_ids().next() {
+            Some(parent) => Some(parent.object()?.into_commit().tree()?),
+            None => None,
+        };
+        let mut files: Vec<String> = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?
+            .iter()
+            .map(|change| change.location().to_string())
+            .collect();
+        files.sort();
+        files.dedup();
+        
+        commits.push(CommitSummary {
+            short_hash: info.id.to_hex_with_len(8).to_string(),
+            author,
+            date,
+            subject,
+            files,
+        });
+    }
+    
+    Ok(commits)
+}
+
+/// Author initials from a name, e.g. "Felix Schütt" -> "FS"
+fn initials(name: &str) -> String {
+    name.split_whitespace()
+        .filter_map(|part| part.chars().next())
+        .flat_map(|c| c.to_uppercase())
+        .take(3)
+        .collect()
+}
+
+/// Human-readable age, e.g. "3d", "5mo", "2y"
+pub fn format_age(days: i64) -> String {
+    if days < 31 {
+        format!("{}d", days)
+    } else if days < 365 {
+        format!("{}mo", days / 30)
+    } else {
+        format!("{}y", days / 365)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_initials() {
+        assert_eq!(initials("Felix Schütt"), "FS");
+        assert_eq!(initials("ada"), "A");
+        assert_eq!(initials(""), "");
+    }
+    
+    #[test]
+    fn test_token_for_host() {
+        let env = |var: &str| match var {
+            "GITHUB_TOKEN" => Some("gh-token".to_string()),
+            "GITLAB_TOKEN" => Some(String::new()),
+            _ => None,
+        };
+        assert_eq!(token_for_host(Some("github.com"), env),
+            Some(("x-access-token".to_string(), "gh-token".to_string())));
+        assert_eq!(token_for_host(Some("gitlab.com"), env), None);
+        assert_eq!(token_for_host(Some("example.com"), env), None);
+    }
+
+    #[test]
+    fn test_resolve_relative_url() {
+        assert_eq!(resolve_relative_url("https://github.com/owner/repo.git", "../other.git"),
+            "https://github.com/owner/other.git");
+        assert_eq!(resolve_relative_url("git@github.com:owner/repo", "../other"),
+            "git@github.com:owner/other");
+        assert_eq!(resolve_relative_url("https://host/a/b", "./c"), "https://host/a/b/c");
+    }
+
+    #[test]
+    fn test_line_changes() {
+        let before = "a\nb\nc\nd\n";
+        let after = "a\nB\nc\nnew\n";
+        assert_eq!(line_changes(before, after), vec![
+            LineChange::Unchanged,
+            LineChange::Modified,
+            LineChange::Unchanged,
+            LineChange::Modified,
+        ]);
+        
+        // `d` was removed from the end
+        let after = "a\nb\nx\nc\n";
+        assert_eq!(line_changes("a\nb\nc\nd\n", after), vec![
+            LineChange::Unchanged,
+            LineChange::Unchanged,
+            LineChange::Added,
+            LineChange::DeletedBelow,
+        ]);
+        
+        assert_eq!(line_changes("a\nb\nc\n", "a\nc\n"), vec![
+            LineChange::Unchanged,
+            LineChange::DeletedAbove,
+        ]);
+        
+        // Truncated file
+        assert_eq!(line_changes("a\nb\nc\nd\n", "a\nb\n"), vec![
+            LineChange::Unchanged,
+            LineChange::DeletedBelow,
+        ]);
+    }
+    
+    #[test]
+    fn test_commit_log_with_merge() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(dir)
+                .stdout(std::process::Stdio::null())
+                .status()
+                .expect("git is installed");
+            assert!(status.success(), "git {:?}", args);
+        };
+        let commit = |message: &str| git(&["commit", "-q", "--allow-empty", "-m", message]);
+        
+        // A - B (v1) - D - M - E
+        //  \             /
+        //   C ----------
+        git(&["init", "-q", "-b", "main"]);
+        commit("A");
+        git(&["checkout", "-q", "-b", "side"]);
+        commit("C");
+        git(&["checkout", "-q", "main"]);
+        commit("B");
+        git(&["tag", "v1"]);
+        commit("D");
+        git(&["merge", "-q", "--no-ff", "side", "-m", "M"]);
+        commit("E");
+        
+        let mut subjects: Vec<String> = commit_log(dir, "v1", "HEAD").unwrap()
+            .into_iter()
+            .map(|c| c.subject)
+            .collect();
+        subjects.sort();
+        // A is an ancestor of v1 and must not be listed, although C leads to it
+        assert_eq!(subjects, ["C", "D", "E", "M"]);
+        
+        // Revisions, not only ref names: HEAD~2 is D
+        let mut subjects: Vec<String> = commit_log(dir, "HEAD~2", "HEAD").unwrap()
+            .into_iter()
+            .map(|c| c.subject)
+            .collect();
+        subjects.sort();
+        assert_eq!(subjects, ["C", "E", "M"]);
+    }
+    
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(3), "3d");
+        assert_eq!(format_age(150), "5mo");
+        assert_eq!(format_age(800), "2y");
+    }
+}
@@ -5,15 +5,21 @@
 use std::fs;
 
 use anyhow::{Context, Result};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use syntect::highlighting::{Theme, Style, FontStyle};
 use syntect::parsing::SyntaxSet;
 use syntect::easy::HighlightLines;
 use syntect::util::LinesWithEndings;
 
-use crate::crate_discovery::CrateInfo;
+use crate::crate_discovery::{CrateInfo, CrateTarget};
+use crate::dep_graph::DependencyGraph;
 use crate::file_classifier::SourceFile;
+use crate::file_stats::{CrateSummary, FileStats};
+use crate::git_ops::{BlameLine, CommitSummary, LineChange, format_age};
+use crate::item_index::{IndexEntry, LINE_ANCHOR_PREFIX};
+use crate::symbol_links::{SymbolLink, SYMBOL_LINK_PREFIX};
+use crate::report::RenderReport;
 
 /// Generate HTML for an entire crate
 pub fn generate_html_for_crate(
@@ -189,10 +195,12 @@ fn generate_html_header(crate_info: &CrateInfo, font_size: f32, columns: u32, th
 /// Collect syntax highlighting spans and unique CSS classes for a file's content.
 /// Returns (all_lines, style_to_class) where all_lines has the highlighted spans
 /// and style_to_class maps StyleKey -> CSS class name.
+/// Lines that fail to highlight are kept as plain text and reported in `warnings`.
 fn collect_highlight_spans(
     content: &str,
     syntax_set: &SyntaxSet,
     theme: &Theme,
+    warnings: &mut Vec<String>,
 ) -> (Vec<Vec<(Style, String)>>, HashMap<StyleKey, String>) {
     let syntax = syntax_set.find_syntax_by_extension("rs")
         .unwrap_or_else(|| syntax_set.find_syntax_plain_text());
@@ -201,9 +209,12 @@ fn collect_highlight_spans(
     let mut style_to_class: HashMap<StyleKey, String> = HashMap::new();
     let mut class_counter = 0usize;
 
-    for line in LinesWithEndings::from(content) {
+    for (line_num, line) in LinesWithEndings::from(content).enumerate() {
         let highlighted = highlighter.highlight_line(line, syntax_set)
-            .unwrap_or_else(|_| vec![(Style::default(), line)]);
+            .unwrap_or_else(|e| {
+                warnings.push(format!("line {}: {}", line_num + 1, e));
+                vec![(Style::default(), line)]
+            });
         let mut line_spans = Vec::new();
         for (style, text) in highlighted {
             let key = StyleKey::from_style(&style);
@@ -218,35 +229,141 @@ fn collect_highlight_spans(
     (all_lines, style_to_class)
 }
 
+/// Extra per-line information rendered in the gutter before the line number, and links
+/// in the line itself
+#[derive(Debug, Clone, Default)]
+pub struct LineAnnotation {
+    /// Gutter text (e.g. blame info)
+    pub gutter: Option<String>,
+    /// CSS color for the gutter text
+    pub gutter_color: Option<String>,
+    /// Change relative to a baseline, rendered as a colored bar in the margin
+    pub change: Option<LineChange>,
+    /// Identifiers linked to their definition, sorted by position
+    pub links: Vec<SymbolLink>,
+    /// Anchor the line number, to find the page the line lands on in the rendered PDF
+    pub anchor: bool,
+}
+
+/// Build gutter annotations from blame information.
+/// With `color_by_age`, recent lines are colored warm and old lines grey.
+pub fn blame_annotations(blame: &[BlameLine], color_by_age: bool) -> Vec<LineAnnotation> {
+    let hash_width = blame.iter().map(|b| b.short_hash.len()).max().unwrap_or(0);
+    blame.iter()
+        .map(|b| LineAnnotation {
+            gutter: Some(format!(
+                "{:<hash_width$} {:<3} {:>4}",
+                b.short_hash,
+                b.author_initials,
+                if b.short_hash.is_empty() { String::new() } else { format_age(b.age_days) },
+                hash_width = hash_width,
+            )),
+            gutter_color: if color_by_age { Some(age_color(b.age_days).to_string()) } else { None },
+            change: None,
+            links: Vec::new(),
+            anchor: false,
+        })
+        .collect()
+}
+
+/// Color for a line of the given age in days
+fn age_color(age_days: i64) -> &'static str {
+    match age_days {
+        0..=30 => "#d73a49",
+        31..=180 => "#e36209",
+        181..=365 => "#b08800",
+        366..=1095 => "#6a737d",
+        _ => "#a0a8b0",
+    }
+}
+
+/// Write the opening of a line: gutter annotation (if any) and line number
+fn write_line_start(html: &mut String, line_num: usize, annotation: Option<&LineAnnotation>) {
+    html.push_str(r#"<span class="line">"#);
+    if let Some(gutter) = annotation.and_then(|a| a.gutter.as_ref()) {
+        match annotation.and_then(|a| a.gutter_color.as_ref()) {
+            Some(color) => html.push_str(&format!(
+                r#"<span class="gutter" style="color: {}">{}</span>"#,
+                color,
+                html_escape(gutter)
+            )),
+            None => html.push_str(&format!(r#"<span class="gutter">{}</span>"#, html_escape(gutter))),
+        }
+    }
+    if let Some(change) = annotation.and_then(|a| a.change) {
+        let class = match change {
+            LineChange::Unchanged => "change-bar",
+            LineChange::Added => "change-bar change-added",
+            LineChange::Modified => "change-bar change-modified",
+            LineChange::DeletedAbove => "change-bar change-deleted",
+            LineChange::DeletedBelow => "change-bar change-deleted-below",
+        };
+        html.push_str(&format!(r#"<span class="{}">&nbsp;</span>"#, class));
+    }
+    if annotation.is_some_and(|a| a.anchor) {
+        html.push_str(&format!(
+            r#"<span class="line-number"><a class="line-anchor" href="{}{}">{}</a></span><span class="line-content">"#,
+            LINE_ANCHOR_PREFIX, line_num, line_num
+        ));
+    } else {
+        html.push_str(&format!(
+            r#"<span class="line-number">{}</span><span class="line-content">"#,
+            line_num
+        ));
+    }
+}
+
 /// Write highlighted lines as HTML spans using CSS classes.
 fn write_highlighted_lines(
     html: &mut String,
     all_lines: &[Vec<(Style, String)>],
     style_to_class: &HashMap<StyleKey, String>,
+    annotations: &[LineAnnotation],
 ) {
     for (line_num, line_spans) in all_lines.iter().enumerate() {
-        html.push_str(&format!(
-            r#"<span class="line"><span class="line-number">{}</span><span class="line-content">"#,
-            line_num + 1
-        ));
+        let annotation = annotations.get(line_num);
+        let links = annotation.map_or(&[][..], |a| &a.links[..]);
+        write_line_start(html, line_num + 1, annotation);
+        let mut offset = 0;
         for (style, text) in line_spans {
             let key = StyleKey::from_style(style);
-            if key.is_default() {
-                html.push_str(&html_escape(text));
-            } else if let Some(class_name) = style_to_class.get(&key) {
-                html.push_str(&format!(
-                    r#"<span class="{}">{}</span>"#,
-                    class_name,
-                    html_escape(text)
-                ));
-            } else {
-                html.push_str(&html_escape(text));
+            let class_name = if key.is_default() { None } else { style_to_class.get(&key) };
+            match class_name {
+                Some(class_name) => {
+                    html.push_str(&format!(r#"<span class="{}">"#, class_name));
+                    write_linked_text(html, text, offset, links);
+                    html.push_str("</span>");
+                }
+                None => write_linked_text(html, text, offset, links),
             }
+            offset += text.len();
         }
         html.push_str("</span></span>\n");
     }
 }
 
+/// Write `text`, which starts at byte `offset` of its line, with the parts covered by
+/// `links` wrapped in links to their definitions
+fn write_linked_text(html: &mut String, text: &str, offset: usize, links: &[SymbolLink]) {
+    let end = offset + text.len();
+    let mut pos = offset;
+    for link in links.iter().filter(|l| l.start < end && l.end > offset) {
+        let (start, stop) = (link.start.max(pos), link.end.min(end));
+        if !text.is_char_boundary(start - offset) || !text.is_char_boundary(stop - offset) {
+            continue;
+        }
+        html.push_str(&html_escape(&text[pos - offset..start - offset]));
+        html.push_str(&format!(
+            r#"<a class="symbol" href="{}{}">{}</a>"#,
+            SYMBOL_LINK_PREFIX,
+            html_escape(&link.target),
+            html_escape(&text[start - offset..stop - offset]),
+        ));
+        pos = stop;
+    }
+    html.push_str(&html_escape(&text[pos - offset..]));
+}
+
 /// Generate CSS class definitions string from style_to_class map.
 fn generate_css_classes(style_to_class: &HashMap<StyleKey, String>) -> String {
     if style_to_class.is_empty() {
@@ -282,11 +399,11 @@ fn generate_html_for_file(
     ));
     
     if let Some(theme) = theme {
-        let (all_lines, style_to_class) = collect_highlight_spans(&content, syntax_set, theme);
+        let (all_lines, style_to_class) = collect_highlight_spans(&content, syntax_set, theme, &mut Vec::new());
         // NOTE: CSS classes for this file won't be in the <head> <style> block.
         // For the crate-mode HTML, we'd need to pre-collect all classes.
         // For now this path uses inline styles as fallback.
-        write_highlighted_lines(&mut html, &all_lines, &style_to_class);
+        write_highlighted_lines(&mut html, &all_lines, &style_to_class, &[]);
     } else {
         for (line_num, line) in LinesWithEndings::from(&content).enumerate() {
             html.push_str(&format!(
@@ -384,15 +501,19 @@ fn html_escape(s: &str) -> String {
 }
 
 /// Generate a minimal HTML document for a single file (no headers, for parallel processing)
+///
+/// `content` is the (possibly formatted) source text to render for `file`,
+/// `annotations` holds optional per-line gutter information (may be empty).
+/// Syntax highlighting problems are appended to `warnings`.
 pub fn generate_html_for_single_file(
     file: &SourceFile,
+    content: &str,
     syntax_set: &SyntaxSet,
     theme: Option<&Theme>,
     font_size: f32,
+    annotations: &[LineAnnotation],
+    warnings: &mut Vec<String>,
 ) -> Result<String> {
-    let content = fs::read_to_string(&file.path)
-        .with_context(|| format!("Failed to read file: {}", file.path.display()))?;
-    
     let (bg_color, fg_color) = if let Some(t) = theme {
         let bg = t.settings.background
             .map(|c| format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b))
@@ -407,7 +528,7 @@ pub fn generate_html_for_single_file(
 
     // Phase 1: Collect syntax highlighting data and CSS classes
     let (all_lines, style_to_class) = if let Some(theme) = theme {
-        let (lines, classes) = collect_highlight_spans(&content, syntax_set, theme);
+        let (lines, classes) = collect_highlight_spans(content, syntax_set, theme, warnings);
         (Some(lines), classes)
     } else {
         (None, HashMap::new())
@@ -469,6 +590,41 @@ pub fn generate_html_for_single_file(
         .line-content {{
             display: inline;
         }}
+        
+        .gutter {{
+            display: inline-block;
+            padding-right: 0.5em;
+            color: #888;
+            font-size: {line_num_size}pt;
+            white-space: pre;
+        }}
+        
+        .change-bar {{
+            display: inline-block;
+            width: 0.4em;
+            margin-right: 0.2em;
+        }}
+        
+        .change-added {{
+            background-color: #2ea043;
+        }}
+        
+        .change-modified {{
+            background-color: #d29922;
+        }}
+        
+        .change-deleted {{
+            border-top: 2px solid #cf222e;
+        }}
+        
+        .change-deleted-below {{
+            border-bottom: 2px solid #cf222e;
+        }}
+        
+        a.symbol, a.line-anchor {{
+            color: inherit;
+            text-decoration: none;
+        }}
 {extra_css}    </style>
 </head>
 <body>
@@ -485,15 +641,13 @@ pub fn generate_html_for_single_file(
     
     // Phase 3: Write highlighted code lines using CSS classes
     if let Some(ref lines) = all_lines {
-        write_highlighted_lines(&mut html, lines, &style_to_class);
+        write_highlighted_lines(&mut html, lines, &style_to_class, annotations);
     } else {
-        for (line_num, line) in LinesWithEndings::from(&content).enumerate() {
-            html.push_str(&format!(
-                r#"<span class="line"><span class="line-number">{}</span><span class="line-content">{}</span></span>
-"#,
-                line_num + 1,
-                html_escape(line)
-            ));
+        for (line_num, line) in LinesWithEndings::from(content).enumerate() {
+            let annotation = annotations.get(line_num);
+            write_line_start(&mut html, line_num + 1, annotation);
+            write_linked_text(&mut html, line, 0, annotation.map_or(&[][..], |a| &a.links[..]));
+            html.push_str("</span></span>\n");
         }
     }
     
@@ -502,12 +656,486 @@ pub fn generate_html_for_single_file(
     Ok(html)
 }
 
+/// Generate HTML for a binary file: a placeholder line, optionally followed by a hex dump
+pub fn generate_html_for_binary_file(
+    file: &SourceFile,
+    size: &str,
+    hex_dump: Option<&str>,
+    font_size: f32,
+) -> String {
+    format!(r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>{path}</title>
+    <style>
+        * {{
+            box-sizing: border-box;
+            margin: 0;
+            padding: 0;
+        }}
+        
+        body {{
+            font-family: 'RobotoMono', monospace;
+            font-size: {font_size}pt;
+            line-height: 1.2;
+            background-color: #ffffff;
+            color: #000000;
+        }}
+        
+        .file-header {{
+            background-color: #e0e0e0;
+            color: #333;
+            padding: 2px 5px;
+            font-weight: bold;
+            font-size: {header_size}pt;
+            border-bottom: 1px solid #999;
+        }}
+        
+        .binary-placeholder {{
+            padding: 2px 5px;
+            color: #888;
+            font-style: italic;
+        }}
+        
+        .code-block {{
+            white-space: pre;
+            font-size: {font_size}pt;
+            font-family: 'RobotoMono', monospace;
+            line-height: 1.15;
+        }}
+    </style>
+</head>
+<body>
+<div class="file-header">{path}</div>
+<div class="binary-placeholder">binary, {size}</div>
+{dump}</body>
+</html>"#,
+        path = html_escape(&file.relative_path.to_string_lossy()),
+        size = html_escape(size),
+        dump = hex_dump
+            .map(|d| format!("<pre class=\"code-block\">{}</pre>\n", html_escape(d)))
+            .unwrap_or_default(),
+        font_size = font_size,
+        header_size = font_size + 1.0,
+    )
+}
+
+/// Generate HTML for a figures section: each image full-width with its path as caption.
+///
+/// `figures` is a list of (image key, caption) pairs; the keys must match the
+/// entries of the images map passed to the PDF renderer.
+pub fn generate_figures_html(
+    title: &str,
+    figures: &[(String, String)],
+    font_size: f32,
+) -> String {
+    let mut body = String::new();
+    for (key, caption) in figures {
+        body.push_str(&format!(
+            r#"<div class="figure"><img src="{}"><div class="caption">{}</div></div>
+"#,
+            html_escape(key),
+            html_escape(caption),
+        ));
+    }
+
+    format!(r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>{name} - Figures</title>
+    <style>
+        * {{
+            box-sizing: border-box;
+            margin: 0;
+            padding: 0;
+        }}
+        
+        body {{
+            font-family: 'RobotoMono', monospace;
+            font-size: {font_size}pt;
+            background-color: #ffffff;
+            color: #333;
+        }}
+        
+        .figure {{
+            margin-bottom: 10px;
+            page-break-inside: avoid;
+        }}
+        
+        .figure img {{
+            width: 100%;
+        }}
+        
+        .caption {{
+            padding: 2px 5px;
+            color: #555;
+            text-align: center;
+        }}
+    </style>
+</head>
+<body>
+{body}</body>
+</html>"#,
+        name = html_escape(title),
+        body = body,
+        font_size = font_size,
+    )
+}
+
+/// Authors, license and repository lines of the title page (empty if none are set)
+fn package_meta_html(crate_info: &CrateInfo) -> String {
+    let mut lines = Vec::new();
+    if !crate_info.authors.is_empty() {
+        lines.push(format!("Authors: {}", html_escape(&crate_info.authors.join(", "))));
+    }
+    if let Some(license) = &crate_info.license {
+        lines.push(format!("License: {}", html_escape(license)));
+    }
+    if let Some(repository) = &crate_info.repository {
+        lines.push(format!("Repository: {}", html_escape(repository)));
+    }
+    if lines.is_empty() {
+        String::new()
+    } else {
+        format!(r#"<div class="package-meta">{}</div>"#, lines.join("<br>"))
+    }
+}
+
+/// Generate a divider page starting the section of a binary target
+pub fn generate_section_page_html(
+    crate_info: &CrateInfo,
+    target: &CrateTarget,
+    font_size: f32,
+) -> String {
+    let path = target.path.strip_prefix(&crate_info.path)
+        .unwrap_or(&target.path)
+        .to_string_lossy()
+        .replace('\\', "/");
+
+    format!(r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>{name} - {target}</title>
+    <style>
+        * {{
+            box-sizing: border-box;
+            margin: 0;
+            padding: 0;
+        }}
+        
+        body {{
+            font-family: 'RobotoMono', monospace;
+            font-size: {font_size}pt;
+            display: flex;
+            flex-direction: column;
+            justify-content: center;
+            align-items: center;
+            min-height: 100vh;
+            background-color: #ffffff;
+            color: #333;
+        }}
+        
+        .section-container {{
+            text-align: center;
+            padding: 40px;
+        }}
+        
+        .crate {{
+            font-size: 14pt;
+            color: #888;
+            margin-bottom: 10px;
+        }}
+        
+        h1 {{
+            font-size: 28pt;
+            margin-bottom: 10px;
+            color: #222;
+        }}
+        
+        .path {{
+            font-size: 12pt;
+            color: #666;
+        }}
+    </style>
+</head>
+<body>
+    <div class="section-container">
+        <div class="crate">{name}</div>
+        <h1>Binary: {target}</h1>
+        <div class="path">{path}</div>
+    </div>
+</body>
+</html>"#,
+        name = html_escape(&crate_info.name),
+        target = html_escape(&target.name),
+        path = html_escape(&path),
+        font_size = font_size,
+    )
+}
+
+/// A page with a heading and a table, shared by the overview and appendix pages.
+///
+/// `body` is the page content (heading, summary line, table), `css` holds the page's own
+/// rules on top of the shared heading, summary and table styles.
+fn table_page_html(title: &str, font_size: f32, css: &str, body: &str) -> String {
+    format!(r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>{title}</title>
+    <style>
+        * {{
+            box-sizing: border-box;
+            margin: 0;
+            padding: 0;
+        }}
+        
+        body {{
+            font-family: 'RobotoMono', monospace;
+            font-size: {font_size}pt;
+            background-color: #ffffff;
+            color: #333;
+        }}
+        
+        h2 {{
+            font-size: 14pt;
+            padding: 6px;
+            background-color: #333;
+            color: white;
+        }}
+        
+        .summary {{
+            padding: 6px;
+            color: #555;
+        }}
+        
+        table {{
+            width: 100%;
+            border-collapse: collapse;
+        }}
+        
+        th, td {{
+            text-align: left;
+            vertical-align: top;
+            padding: 2px 4px;
+            border-bottom: 1px solid #ddd;
+        }}
+        
+        th {{
+            background-color: #e0e0e0;
+        }}
+        
+        {css}
+    </style>
+</head>
+<body>
+    {body}
+</body>
+</html>"#,
+        title = html_escape(title),
+        font_size = font_size,
+        css = css,
+        body = body,
+    )
+}
+
+/// Maximum number of touched files listed per commit in the commit log
+const MAX_LOG_FILES: usize = 8;
+
+/// Generate a commit log appendix: one table row per commit touching the crate.
+///
+/// `prefix` is the crate directory relative to the repository root (empty for the root),
+/// touched files are listed relative to it.
+pub fn generate_commit_log_html(
+    crate_info: &CrateInfo,
+    range: &str,
+    commits: &[CommitSummary],
+    prefix: &str,
+    font_size: f32,
+) -> String {
+    let mut rows = String::new();
+    let mut count = 0;
+    for commit in commits {
+        let files: Vec<&str> = commit.files_under(prefix)
+            .map(|f| f.strip_prefix(prefix).unwrap_or(f).trim_start_matches('/'))
+            .collect();
+        if files.is_empty() {
+            continue;
+        }
+        count += 1;
+        
+        let mut files_html = files.iter()
+            .take(MAX_LOG_FILES)
+            .map(|f| html_escape(f))
+            .collect::<Vec<_>>()
+            .join("<br>");
+        if files.len() > MAX_LOG_FILES {
+            files_html.push_str(&format!("<br>(+{} more)", files.len() - MAX_LOG_FILES));
+        }
+        
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td class=\"files\">{}</td></tr>\n",
+            html_escape(&commit.short_hash),
+            html_escape(&commit.author),
+            html_escape(&commit.date),
+            html_escape(&commit.subject),
+            files_html,
+        ));
+    }
+
+    let body = format!(r#"<h2>Commit log: {name}</h2>
+    <p class="summary">{count} commit(s) in {range}</p>
+    <table>
+        <tr><th>Commit</th><th>Author</th><th>Date</th><th>Subject</th><th>Files</th></tr>
+        {rows}
+    </table>"#,
+        name = html_escape(&crate_info.name),
+        range = html_escape(range),
+        count = count,
+        rows = rows,
+    );
+    let css = ".files { color: #666; }";
+    table_page_html(&format!("{} - Commit Log", crate_info.name), font_size, css, &body)
+}
+
+/// Size of one crate's printout at one ref, for the ref comparison summary
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CrateStats {
+    pub files: usize,
+    pub loc: usize,
+    pub pages: usize,
+}
+
+/// Format a change against the first ref, e.g. "+120" or "-3"
+fn format_delta(value: usize, base: Option<usize>) -> String {
+    match base {
+        Some(base) if value != base => format!(" ({:+})", value as i64 - base as i64),
+        _ => String::new(),
+    }
+}
+
+/// Generate a summary page comparing crates across several refs.
+///
+/// `refs` holds (ref name, short hash) pairs in the order given on the command line,
+/// `stats` maps crate name -> ref name -> statistics. Crates missing at a ref are shown as "-",
+/// differences are relative to the first ref.
+pub fn generate_ref_comparison_html(
+    refs: &[(String, String)],
+    stats: &BTreeMap<String, BTreeMap<String, CrateStats>>,
+    font_size: f32,
+) -> String {
+    let mut header = String::from("<tr><th>Crate</th>");
+    for (name, hash) in refs {
+        header.push_str(&format!("<th>{}<br><span class=\"hash\">{}</span></th>", html_escape(name), html_escape(hash)));
+    }
+    header.push_str("</tr>");
+
+    let mut rows = String::new();
+    for (crate_name, per_ref) in stats {
+        rows.push_str(&format!("<tr><td>{}</td>", html_escape(crate_name)));
+        let base = refs.first().and_then(|(name, _)| per_ref.get(name));
+        for (name, _) in refs {
+            match per_ref.get(name) {
+                Some(s) => rows.push_str(&format!(
+                    "<td>{} files{}<br>{} LOC{}<br>{} pages{}</td>",
+                    s.files, format_delta(s.files, base.map(|b| b.files)),
+                    s.loc, format_delta(s.loc, base.map(|b| b.loc)),
+                    s.pages, format_delta(s.pages, base.map(|b| b.pages)),
+                )),
+                None => rows.push_str("<td class=\"missing\">-</td>"),
+            }
+        }
+        rows.push_str("</tr>\n");
+    }
+
+    let body = format!(r#"<h2>Ref comparison</h2>
+    <table>
+        {header}
+        {rows}
+    </table>"#,
+        header = header,
+        rows = rows,
+    );
+    let css = ".hash, .missing { color: #888; }";
+    table_page_html("Ref Comparison", font_size, css, &body)
+}
+
+/// Generate the workspace overview page: the dependency graph as a diagram (the SVG
+/// image `diagram_key`, omitted if None) and a table of each crate's dependencies and
+/// dependents, in printing order.
+pub fn generate_dependency_graph_html(
+    title: &str,
+    graph: &DependencyGraph,
+    diagram_key: Option<&str>,
+    font_size: f32,
+) -> String {
+    let names = |indices: &[usize]| if indices.is_empty() {
+        "-".to_string()
+    } else {
+        indices.iter()
+            .map(|&i| html_escape(&graph.crates()[i].name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut rows = String::new();
+    for i in graph.topological_order() {
+        let crate_info = graph.crates()[i];
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            html_escape(&crate_info.name),
+            html_escape(&crate_info.version),
+            names(graph.dependencies(i)),
+            names(&graph.dependents(i)),
+        ));
+    }
+    let diagram = diagram_key
+        .map(|key| format!(r#"<div class="diagram"><img src="{}"></div>"#, html_escape(key)))
+        .unwrap_or_default();
+
+    let body = format!(r#"<h2>{title}: crate dependencies</h2>
+    {diagram}
+    <table>
+        <tr><th>Crate</th><th>Version</th><th>Depends on</th><th>Used by</th></tr>
+        {rows}
+    </table>"#,
+        title = html_escape(title),
+        diagram = diagram,
+        rows = rows,
+    );
+    let css = ".diagram { padding: 10px; text-align: center; page-break-inside: avoid; }\n\
+               .diagram img { max-width: 100%; }";
+    table_page_html(&format!("{} - Workspace", title), font_size, css, &body)
+}
+
+/// Maximum number of uncommitted files listed on the title page
+const MAX_TITLE_MODIFIED_FILES: usize = 20;
+
 /// Generate a title page HTML for a crate
 pub fn generate_title_page_html(
     crate_info: &CrateInfo,
     git_hash: Option<&str>,
+    skipped_submodules: &[String],
+    modified_files: &[String],
+    public_api: bool,
     font_size: f32,
 ) -> String {
+    let modified_html = if modified_files.is_empty() {
+        String::new()
+    } else {
+        let mut files = modified_files.iter()
+            .take(MAX_TITLE_MODIFIED_FILES)
+            .map(|f| html_escape(f))
+            .collect::<Vec<_>>()
+            .join("<br>");
+        if modified_files.len() > MAX_TITLE_MODIFIED_FILES {
+            files.push_str(&format!("<br>(+{} more)", modified_files.len() - MAX_TITLE_MODIFIED_FILES));
+        }
+        format!(r#"<div class="modified">Uncommitted changes:<br>{}</div>"#, files)
+    };
+
     format!(r#"<!DOCTYPE html>
 <html lang="en">
 <head>
@@ -562,29 +1190,190 @@ pub fn generate_title_page_html(
             max-width: 600px;
             line-height: 1.5;
         }}
+        
+        .submodules {{
+            font-size: 10pt;
+            color: #888;
+            margin-top: 20px;
+        }}
+        
+        .modified {{
+            font-size: 10pt;
+            color: #a04000;
+            margin-bottom: 20px;
+        }}
+        
+        .package-meta {{
+            font-size: 10pt;
+            color: #666;
+            margin-top: 20px;
+            line-height: 1.5;
+        }}
     </style>
 </head>
 <body>
     <div class="title-container">
         <h1>{name}</h1>
-        <div class="version">Version {version}</div>
+        <div class="version">Version {version} &middot; Edition {edition}</div>
+        {public_api_html}
         {git_hash_html}
+        {modified_html}
         {description_html}
+        {package_meta_html}
+        {submodules_html}
     </div>
 </body>
 </html>"#,
         name = html_escape(&crate_info.name),
         version = html_escape(&crate_info.version),
+        edition = html_escape(&crate_info.edition),
+        package_meta_html = package_meta_html(crate_info),
+        public_api_html = if public_api {
+            r#"<div class="version">Public API</div>"#
+        } else {
+            ""
+        },
         git_hash_html = git_hash
             .map(|h| format!(r#"<div class="git-hash">Commit: {}</div>"#, html_escape(h)))
             .unwrap_or_default(),
         description_html = crate_info.description.as_ref()
             .map(|d| format!(r#"<div class="description">{}</div>"#, html_escape(d)))
             .unwrap_or_default(),
+        modified_html = modified_html,
+        submodules_html = if skipped_submodules.is_empty() {
+            String::new()
+        } else {
+            format!(r#"<div class="submodules">Submodules not included: {}</div>"#,
+                skipped_submodules.iter().map(|p| html_escape(p)).collect::<Vec<_>>().join(", "))
+        },
         font_size = font_size,
     )
 }
 
+/// Generate an appendix page listing files that are missing or degraded in the printout
+pub fn generate_report_page_html(
+    crate_info: &CrateInfo,
+    report: &RenderReport,
+    font_size: f32,
+) -> String {
+    let mut rows = String::new();
+    for issue in report.sorted() {
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            html_escape(&issue.path),
+            html_escape(issue.kind.label()),
+            html_escape(&issue.message),
+        ));
+    }
+
+    let body = format!(r#"<h2>Processing report: {name}</h2>
+    <p class="summary">{count} problem(s), {missing} file(s) missing from this printout.</p>
+    <table>
+        <tr><th>File</th><th>Problem</th><th>Details</th></tr>
+        {rows}
+    </table>"#,
+        name = html_escape(&crate_info.name),
+        count = report.issues.len(),
+        missing = report.missing_count(),
+        rows = rows,
+    );
+    table_page_html(&format!("{} - Processing Report", crate_info.name), font_size, "", &body)
+}
+
+/// Generate the index appendix: every indexed item with its file, line and page, in
+/// alphabetical order with a heading row per initial letter
+pub fn generate_index_html(
+    crate_info: &CrateInfo,
+    entries: &[IndexEntry],
+    font_size: f32,
+) -> String {
+    let mut rows = String::new();
+    let mut letter = None;
+    for entry in entries {
+        let initial = entry.item.name.chars().next().map(|c| c.to_ascii_uppercase());
+        if initial != letter {
+            letter = initial;
+            rows.push_str(&format!(
+                "<tr class=\"letter\"><td colspan=\"4\">{}</td></tr>\n",
+                html_escape(&initial.map(String::from).unwrap_or_default()),
+            ));
+        }
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}:{}</td><td>{}</td></tr>\n",
+            html_escape(&entry.item.name),
+            html_escape(&entry.item.kind.to_string()),
+            html_escape(&entry.path),
+            entry.item.line,
+            entry.page.map(|page| page.to_string()).unwrap_or_default(),
+        ));
+    }
+
+    let body = format!(r#"<h2>Index: {name}</h2>
+    <p class="summary">{count} item(s)</p>
+    <table>
+        <tr><th>Item</th><th>Kind</th><th>Defined at</th><th>Page</th></tr>
+        {rows}
+    </table>"#,
+        name = html_escape(&crate_info.name),
+        count = entries.len(),
+        rows = rows,
+    );
+    let css = ".letter td { font-weight: bold; padding-top: 6px; border-bottom: 1px solid #999; }";
+    table_page_html(&format!("{} - Index", crate_info.name), font_size, css, &body)
+}
+
+/// Pages of a file as shown in the summary: "4", "4-7", or "-" for a file without pages
+fn page_range(first_page: usize, pages: usize) -> String {
+    match pages {
+        0 => "-".to_string(),
+        1 => first_page.to_string(),
+        _ => format!("{}-{}", first_page, first_page + pages - 1),
+    }
+}
+
+fn stats_cells(stats: &FileStats) -> String {
+    format!(
+        "<td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>",
+        stats.lines, stats.code, stats.comments, stats.blank, stats.items, stats.unsafe_blocks,
+    )
+}
+
+/// Generate the summary page printed after the title page: each file with its
+/// statistics and pages, so it doubles as the table of contents
+pub fn generate_summary_page_html(
+    crate_info: &CrateInfo,
+    summary: &CrateSummary,
+    font_size: f32,
+) -> String {
+    let mut rows = String::new();
+    for file in &summary.files {
+        rows.push_str(&format!(
+            "<tr><td>{}</td>{}<td>{}</td></tr>\n",
+            html_escape(&file.path),
+            stats_cells(&file.stats),
+            page_range(file.first_page, file.pages),
+        ));
+    }
+    let pages: usize = summary.files.iter().map(|f| f.pages).sum();
+
+    let body = format!(r#"<h2>Summary: {name}</h2>
+    <p class="summary">{count} file(s), {lines} lines on {pages} page(s)</p>
+    <table>
+        <tr><th>File</th><th>Lines</th><th>Code</th><th>Comments</th><th>Blank</th><th>Items</th><th>Unsafe</th><th>Pages</th></tr>
+        {rows}
+        <tr class="total"><td>Total</td>{total}<td>{pages}</td></tr>
+    </table>"#,
+        name = html_escape(&crate_info.name),
+        count = summary.files.len(),
+        lines = summary.total.lines,
+        pages = pages,
+        rows = rows,
+        total = stats_cells(&summary.total),
+    );
+    let css = ".total td { font-weight: bold; border-top: 1px solid #999; }";
+    table_page_html(&format!("{} - Summary", crate_info.name), font_size, css, &body)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -595,4 +1384,38 @@ mod tests {
         assert_eq!(html_escape("a & b"), "a &amp; b");
         assert_eq!(html_escape("\"test\""), "&quot;test&quot;");
     }
+
+    #[test]
+    fn test_write_linked_text() {
+        let links = [SymbolLink { start: 7, end: 10, target: "Point::new".to_string() }];
+        let mut html = String::new();
+        write_linked_text(&mut html, "Point::", 0, &links);
+        write_linked_text(&mut html, "new()<", 7, &links);
+        assert_eq!(html, r##"Point::<a class="symbol" href="#sym-Point::new">new</a>()&lt;"##);
+    }
+
+    #[test]
+    fn test_line_anchor() {
+        let mut html = String::new();
+        write_line_start(&mut html, 7, Some(&LineAnnotation { anchor: true, ..Default::default() }));
+        assert!(html.ends_with(r##"<span class="line-number"><a class="line-anchor" href="#line-7">7</a></span><span class="line-content">"##));
+        html.clear();
+        write_line_start(&mut html, 7, None);
+        assert!(!html.contains("<a "));
+    }
+
+    #[test]
+    fn test_format_delta() {
+        assert_eq!(format_delta(120, Some(100)), " (+20)");
+        assert_eq!(format_delta(97, Some(100)), " (-3)");
+        assert_eq!(format_delta(100, Some(100)), "");
+        assert_eq!(format_delta(100, None), "");
+    }
+
+    #[test]
+    fn test_page_range() {
+        assert_eq!(page_range(4, 0), "-");
+        assert_eq!(page_range(4, 1), "4");
+        assert_eq!(page_range(4, 3), "4-6");
+    }
 }
@@ -0,0 +1,219 @@
+//! Item index for `--index`
+//!
+//! Rust files are parsed with syn after formatting, so the line numbers of the indexed
+//! functions, types, traits and impls match the printed ones. The index appendix lists
+//! each item with its file, line and page.
+//!
+//! Pages are read back from the rendered PDFs: the line number of each definition is an
+//! `<a href="#line-N">` anchor, whose link annotation ends up on the page the line was
+//! laid out on (see [`anchored_line_pages`]).
+
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{Context, Result};
+use printpdf::{Actions, Op, PdfDocument};
+use quote::ToTokens;
+use syn::{ImplItem, Item, TraitItem, Type};
+
+/// Kind of an indexed item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItemKind {
+    Function,
+    /// A function in an impl or trait, indexed as `Type::name`
+    Method,
+    Struct,
+    Enum,
+    Union,
+    Trait,
+    TypeAlias,
+    Macro,
+    /// An impl block, indexed under its self type, with the implemented trait if any
+    Impl { trait_name: Option<String> },
+}
+
+impl fmt::Display for ItemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ItemKind::Function => f.write_str("fn"),
+            ItemKind::Method => f.write_str("method"),
+            ItemKind::Struct => f.write_str("struct"),
+            ItemKind::Enum => f.write_str("enum"),
+            ItemKind::Union => f.write_str("union"),
+            ItemKind::Trait => f.write_str("trait"),
+            ItemKind::TypeAlias => f.write_str("type"),
+            ItemKind::Macro => f.write_str("macro"),
+            ItemKind::Impl { trait_name: None } => f.write_str("impl"),
+            ItemKind::Impl { trait_name: Some(name) } => write!(f, "impl {}", name),
+        }
+    }
+}
+
+/// An item defined in a file
+#[derive(Debug, Clone)]
+pub struct IndexedItem {
+    /// Name, prefixed with its inline modules (`tests::helper`) or type (`Point::new`)
+    pub name: String,
+    pub kind: ItemKind,
+    /// Line of the item's name (1-based)
+    pub line: usize,
+}
+
+/// An indexed item with its location in the printout
+#[derive(Debug, Clone)]
+pub struct IndexEntry {
+    pub item: IndexedItem,
+    /// File path relative to the crate root
+    pub path: String,
+    /// Page in the PDF (1-based), unless the renderer dropped the line's anchor
+    pub page: Option<usize>,
+}
+
+/// `href` prefix of the anchors on the definition lines of indexed items
+pub const LINE_ANCHOR_PREFIX: &str = "#line-";
+
+/// Parse a Rust file and list the items it defines, in source order
+pub fn index_items(content: &str) -> Result<Vec<IndexedItem>> {
+    let file = syn::parse_file(content).context("Failed to parse file")?;
+    let mut items = Vec::new();
+    collect_items(&file.items, "", &mut items);
+    Ok(items)
+}
+
+fn collect_items(items: &[Item], prefix: &str, out: &mut Vec<IndexedItem>) {
+    let mut push = |name: String, kind: ItemKind, line: usize| {
+        out.push(IndexedItem { name: format!("{}{}", prefix, name), kind, line });
+    };
+    let mut nested = Vec::new();
+    for item in items {
+        match item {
+            Item::Fn(f) => push(f.sig.ident.to_string(), ItemKind::Function, line_of(&f.sig.ident)),
+            Item::Struct(s) => push(s.ident.to_string(), ItemKind::Struct, line_of(&s.ident)),
+            Item::Enum(e) => push(e.ident.to_string(), ItemKind::Enum, line_of(&e.ident)),
+            Item::Union(u) => push(u.ident.to_string(), ItemKind::Union, line_of(&u.ident)),
+            Item::Type(t) => push(t.ident.to_string(), ItemKind::TypeAlias, line_of(&t.ident)),
+            Item::Macro(m) => {
+                if let Some(ident) = &m.ident {
+                    push(ident.to_string(), ItemKind::Macro, line_of(ident));
+                }
+            }
+            Item::Trait(t) => {
+                push(t.ident.to_string(), ItemKind::Trait, line_of(&t.ident));
+                for trait_item in &t.items {
+                    if let TraitItem::Fn(f) = trait_item {
+                        push(format!("{}::{}", t.ident, f.sig.ident), ItemKind::Method, line_of(&f.sig.ident));
+                    }
+                }
+            }
+            Item::Impl(i) => {
+                let self_name = type_name(&i.self_ty);
+                let trait_name = i.trait_.as_ref()
+                    .and_then(|(_, path, _)| path.segments.last())
+                    .map(|segment| segment.ident.to_string());
+                push(self_name.clone(), ItemKind::Impl { trait_name }, line_of(&i.impl_token));
+                for impl_item in &i.items {
+                    if let ImplItem::Fn(f) = impl_item {
+                        push(format!("{}::{}", self_name, f.sig.ident), ItemKind::Method, line_of(&f.sig.ident));
+                    }
+                }
+            }
+            Item::Mod(m) => {
+                if let Some((_, items)) = &m.content {
+                    nested.push((format!("{}{}::", prefix, m.ident), items));
+                }
+            }
+            _ => {}
+        }
+    }
+    for (prefix, items) in nested {
+        collect_items(items, &prefix, out);
+    }
+}
+
+/// Name of an impl's self type: the last path segment without generics (`Vec<T>` -> `Vec`)
+fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Path(path) => path.path.segments.last()
+            .map(|segment| segment.ident.to_string())
+            .unwrap_or_default(),
+        Type::Reference(reference) => type_name(&reference.elem),
+        _ => ty.to_token_stream().to_string(),
+    }
+}
+
+fn line_of(tokens: &impl syn::spanned::Spanned) -> usize {
+    tokens.span().start().line
+}
+
+/// Pages of the anchored lines in a rendered file (line -> 0-based page in `doc`). The
+/// anchors are removed, they only mark positions and are not meant to be followed.
+pub fn anchored_line_pages(doc: &mut PdfDocument) -> HashMap<usize, usize> {
+    let mut pages = HashMap::new();
+    for (index, page) in doc.pages.iter_mut().enumerate() {
+        page.ops.retain(|op| {
+            let Op::LinkAnnotation { link } = op else {
+                return true;
+            };
+            let line = match &link.actions {
+                Actions::Uri(uri) => uri.strip_prefix(LINE_ANCHOR_PREFIX).and_then(|line| line.parse::<usize>().ok()),
+                _ => None,
+            };
+            match line {
+                Some(line) => {
+                    pages.entry(line).or_insert(index);
+                    false
+                }
+                None => true,
+            }
+        });
+    }
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_items() {
+        let source = "\
+struct Point {
+    x: f32,
+}
+
+impl Point {
+    fn new() -> Self {
+        Point { x: 0.0 }
+    }
+}
+
+impl std::fmt::Debug for Point {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Ok(())
+    }
+}
+
+fn parse_margins() {}
+
+mod tests {
+    fn helper() {}
+}
+";
+        let items: Vec<(String, String, usize)> = index_items(source).unwrap().into_iter()
+            .map(|item| (item.name, item.kind.to_string(), item.line))
+            .collect();
+        let expected = [
+            ("Point", "struct", 1),
+            ("Point", "impl", 5),
+            ("Point::new", "method", 6),
+            ("Point", "impl Debug", 11),
+            ("Point::fmt", "method", 12),
+            ("parse_margins", "fn", 17),
+            ("tests::helper", "fn", 20),
+        ];
+        assert_eq!(items.len(), expected.len());
+        for (item, (name, kind, line)) in items.iter().zip(expected) {
+            assert_eq!((item.0.as_str(), item.1.as_str(), item.2), (name, kind, line));
+        }
+    }
+}
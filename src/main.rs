@@ -4,42 +4,77 @@
 //! classifies source files vs test files, generates syntax-highlighted HTML,
 //! and converts them to PDF using printpdf's HTML layout engine.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
+use std::num::NonZeroU32;
 use std::path::{Path, PathBuf};
-use std::process::Command;
 use std::sync::Arc;
 use std::time::Instant;
 
 use anyhow::{Context, Result, bail};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use ignore::WalkBuilder;
 use printpdf::{Base64OrRaw, GeneratePdfOptions, PdfDocument, PdfParseOptions, PdfSaveOptions};
 use syntect::highlighting::{Theme, ThemeSet};
 use syntect::parsing::SyntaxSet;
 
+mod clone_cache;
 mod crate_discovery;
+mod crate_filter;
+mod dep_graph;
 mod file_classifier;
+mod file_content;
+mod file_stats;
+mod formatter;
 mod git_ops;
 mod html_generator;
-
-use crate_discovery::{CrateInfo, discover_crates};
-use file_classifier::{classify_files, SourceFile, FileCategory};
-use git_ops::{clone_or_open_repo, checkout_ref, get_git_hash};
-use html_generator::{generate_html_for_single_file, generate_title_page_html};
+mod item_index;
+mod public_api;
+mod report;
+mod symbol_links;
+#[cfg(test)]
+mod test_util;
+
+use clone_cache::CloneCache;
+use crate_discovery::{CrateInfo, RootManifest, TargetKind, DEFAULT_SKIP_PATTERNS, discover_crates, discover_crates_with_metadata};
+use crate_filter::CrateFilter;
+use dep_graph::DependencyGraph;
+use file_classifier::{classify_files, find_image_files, ImageFile, SourceFile, FileCategory};
+use file_content::{FileContent, format_size, hex_dump, load_file};
+use file_stats::{CrateSummary, FileStats, FileSummary};
+use formatter::format_or_original;
+use git_ops::{
+    blame_file, changes_since, clone_or_open_repo, checkout_ref, commit_log, default_branch, export_ref,
+    export_index, fetch_submodule, format_age, get_git_hash, is_bare_repo, line_changes, list_refs,
+    list_submodules, open_blame_repo, sparse_checkout, worktree_status, CloneOptions, LineChange, SubmoduleInfo,
+};
+use html_generator::{
+    blame_annotations, generate_commit_log_html, generate_figures_html, generate_html_for_binary_file,
+    generate_dependency_graph_html, generate_html_for_single_file, generate_index_html, generate_ref_comparison_html,
+    generate_report_page_html, generate_section_page_html, generate_summary_page_html, generate_title_page_html,
+    CrateStats, LineAnnotation,
+};
+use item_index::{anchored_line_pages, index_items, IndexEntry, IndexedItem};
+use report::{FileIssue, IssueKind, RenderReport};
+use symbol_links::{link_symbols, link_targets, SymbolTable};
 
 /// git2pdf - Print git repositories to PDF for code review
 #[derive(Parser, Debug)]
 #[command(name = "git2pdf")]
 #[command(author, version, about, long_about = None)]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Git repository URL or local file path
     #[arg(value_name = "SOURCE", required_unless_present = "file")]
     source: Option<String>,
 
-    /// Branch, tag, or commit to checkout (default: tries 'main', then 'master')
-    #[arg(short, long)]
-    r#ref: Option<String>,
+    /// Branch, tag, or commit to checkout (default: the remote's default branch).
+    /// Repeat (or comma-separate) to print several refs and compare them
+    #[arg(short, long, value_delimiter = ',')]
+    r#ref: Vec<String>,
 
     /// Output directory for generated PDFs (default: current directory)
     #[arg(short, long, default_value = ".")]
@@ -73,15 +108,42 @@ struct Args {
     #[arg(short, long)]
     verbose: bool,
 
-    /// Only process specific crates (comma-separated)
+    /// Only process specific crates (comma-separated names, globs like "azul-*", paths like
+    /// "crates/core", "!" to exclude)
     #[arg(long)]
     crates: Option<String>,
 
-    /// Temporary directory for cloning (default: system temp)
+    /// Also process the crates the selected crates depend on (path dependencies)
+    #[arg(long, conflicts_with = "sparse")]
+    with_deps: bool,
+
+    /// Only process the workspace's default-members
     #[arg(long)]
+    default_members: bool,
+
+    /// Discover crates with `cargo metadata` (exact targets, features and edition, requires cargo)
+    #[arg(long)]
+    cargo_metadata: bool,
+
+    /// Skip crates below directories matching these globs when searching a repository
+    /// without a root Cargo.toml (comma-separated, relative to the repository root)
+    #[arg(long, value_delimiter = ',', default_value = DEFAULT_SKIP_PATTERNS)]
+    skip: Vec<String>,
+
+    /// Only print the public API: `pub` items with their doc comments and signatures,
+    /// bodies elided (always formatted with rustfmt, written to `<crate>-api.pdf`)
+    #[arg(long, conflicts_with_all = ["blame", "blame_colors", "since", "include_tests"])]
+    public_api: bool,
+
+    /// Print all crates into one PDF, starting with a dependency graph page, in dependency order
+    #[arg(long)]
+    combine: bool,
+
+    /// Temporary directory for cloning (default: system temp)
+    #[arg(long, global = true)]
     temp_dir: Option<PathBuf>,
 
-    /// Skip running cargo fmt on cloned repositories
+    /// Skip formatting source files with rustfmt before rendering
     #[arg(long)]
     no_fmt: bool,
 
@@ -104,6 +166,190 @@ struct Args {
     /// Process a single file directly (bypasses git/crate logic, for benchmarking)
     #[arg(long)]
     file: Option<PathBuf>,
+
+    /// Append a page listing files that could not be formatted, highlighted or rendered
+    #[arg(long)]
+    report_page: bool,
+
+    /// Append an index of the crate's functions, types, traits and impls with the file,
+    /// line and page of their definition
+    #[arg(long)]
+    index: bool,
+
+    /// Link identifiers in the code to the page of their definition, for reading the PDF
+    /// on screen
+    #[arg(long)]
+    links: bool,
+
+    /// Add a summary page after the title page listing each file with its lines of code,
+    /// comments, blank lines, items, unsafe blocks and pages
+    #[arg(long)]
+    summary: bool,
+
+    /// Write the per-file statistics and pages as JSON next to the PDF
+    #[arg(long)]
+    json: bool,
+
+    /// Render binary files up to this many bytes as a hex dump (default: placeholder only).
+    /// With --include-tests, binary fixtures of at most this size in tests/ are printed too.
+    #[arg(long, value_name = "BYTES")]
+    hex_dump: Option<u64>,
+
+    /// Include image files (PNG, JPEG, GIF, BMP, SVG) from the crate and the repository as full-width figures
+    #[arg(long)]
+    images: bool,
+
+    /// Show git blame (commit, author initials, age) in the gutter; disables formatting
+    #[arg(long)]
+    blame: bool,
+
+    /// Color blame annotations by age (implies --blame)
+    #[arg(long)]
+    blame_colors: bool,
+
+    /// Mark lines changed since this branch, tag or commit (any revision, e.g. HEAD~5) with a colored bar in the margin
+    #[arg(long, value_name = "REF")]
+    since: Option<String>,
+
+    /// Append a commit log for a range of refs, e.g. "v1.0..v1.1", or "v1.0" for v1.0..HEAD
+    #[arg(long, value_name = "FROM..TO")]
+    commit_log: Option<String>,
+
+    /// Shallow clone: only fetch the last N commits of remote repositories.
+    /// Options that need history (--blame, --since, --commit-log) only see these commits
+    #[arg(long, value_name = "N")]
+    depth: Option<NonZeroU32>,
+
+    /// Only check out the directories of the crates selected with --crates (remote repositories)
+    #[arg(long)]
+    sparse: bool,
+
+    /// Fetch submodules that are not checked out, so their crates are printed too
+    #[arg(long)]
+    submodules: bool,
+
+    /// SSH private key for git@ and ssh:// URLs (default: ssh agent and ~/.ssh/config).
+    /// HTTPS remotes use git credential helpers, or a token from GIT2PDF_TOKEN,
+    /// GITHUB_TOKEN or GITLAB_TOKEN
+    #[arg(long, value_name = "PATH")]
+    ssh_key: Option<PathBuf>,
+
+    /// Discard the cached clone of a remote repository and clone it again
+    #[arg(long, conflicts_with = "offline")]
+    refresh: bool,
+
+    /// Use the cached clone of a remote repository without fetching
+    #[arg(long)]
+    offline: bool,
+
+    /// List the branches and tags of the repository and exit
+    #[arg(long)]
+    list_refs: bool,
+
+    /// Print the staged (index) version of a local repository instead of the working tree
+    #[arg(long, conflicts_with = "ref")]
+    staged: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Manage the cache of cloned remote repositories
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheAction {
+    /// List cached clones
+    List,
+    /// Remove cached clones that haven't been used for a while
+    Prune {
+        /// Remove clones not used for this many days
+        #[arg(long, value_name = "DAYS", default_value = "30")]
+        older_than: u64,
+
+        /// Remove all clones
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+/// A source file rendered to an individual PDF in the cache directory
+struct CachedFile {
+    /// Path relative to the crate root
+    path: String,
+    /// Location of the per-file PDF
+    cache_path: PathBuf,
+    /// Binary target section the file is printed in
+    section: Option<String>,
+    /// Line, comment and item counts of the printed text
+    stats: FileStats,
+    /// Pages of the per-file PDF
+    pages: usize,
+    /// Items defined in the file, for the index and symbol links (only with --index or --links)
+    items: Vec<IndexedItem>,
+    /// Page of each item's line within the per-file PDF (line -> 0-based page)
+    item_pages: HashMap<usize, usize>,
+    /// Time taken to render the file
+    elapsed: std::time::Duration,
+    /// Non-fatal problems (formatting, highlighting)
+    issues: Vec<FileIssue>,
+}
+
+/// A version of the repository to print
+struct RefTarget {
+    /// Ref name, set when several refs are printed side by side
+    label: Option<String>,
+    /// Directory containing the files at this ref
+    work_dir: PathBuf,
+    /// Short commit hash for the title page
+    git_hash: Option<String>,
+    /// Submodules that are not part of the printout
+    skipped_submodules: Vec<String>,
+    /// Symlinks left out of a git export, relative to the repository root
+    skipped_symlinks: Vec<String>,
+    /// Files that differ from the commit (uncommitted changes), relative to the repository root
+    modified_files: Vec<String>,
+}
+
+/// Fill the empty submodule directories in `work_dir`, fetching the submodules into
+/// mirrors below `mirrors` if `fetch` options are given. Returns the paths of the
+/// submodules left out of the printout.
+fn populate_submodules(
+    submodules: &[SubmoduleInfo],
+    work_dir: &Path,
+    mirrors: &Path,
+    fetch: Option<&CloneOptions>,
+    verbose: bool,
+) -> Vec<String> {
+    let mut skipped = Vec::new();
+    for submodule in submodules {
+        let submodule_dir = work_dir.join(&submodule.path);
+        let populated = fs::read_dir(&submodule_dir)
+            .map(|mut entries| entries.next().is_some())
+            .unwrap_or(false);
+        if populated {
+            continue;
+        }
+        if let Some(options) = fetch {
+            let mirror = mirrors.join(sanitize_file_name(&submodule.path));
+            match fetch_submodule(submodule, &submodule_dir, &mirror, options, verbose) {
+                Ok(()) => continue,
+                Err(e) => eprintln!("Warning: Could not fetch submodule {}: {:#}", submodule.path, e),
+            }
+        }
+        skipped.push(submodule.path.clone());
+    }
+    skipped
+}
+
+/// Make a ref name usable in a file name ("feature/x" -> "feature-x")
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '-' })
+        .collect()
 }
 
 /// Parse paper size from "WIDTHxHEIGHT" format (in mm)
@@ -119,6 +365,20 @@ fn parse_paper_size(s: &str) -> Result<(f32, f32)> {
     Ok((width, height))
 }
 
+/// Parse a commit range "FROM..TO", or "FROM" for FROM..HEAD
+fn parse_commit_range(s: &str) -> Result<(String, String)> {
+    let s = s.trim();
+    let (from, to) = match s.split_once("..") {
+        Some((from, to)) => (from, to),
+        None => (s, "HEAD"),
+    };
+    let is_ref = |name: &str| !name.is_empty() && !name.contains(char::is_whitespace) && !name.contains("..");
+    if !is_ref(from) || !is_ref(to) || to.starts_with('.') {
+        bail!("Invalid commit range \"{}\". Expected FROM..TO with both sides set (e.g., v1.0..v1.1), or FROM for FROM..HEAD", s);
+    }
+    Ok((from.to_string(), to.to_string()))
+}
+
 /// Parse margins from CSS-style format (in mm)
 /// Accepts: "all", "vertical horizontal", or "top right bottom left"
 fn parse_margins(s: &str) -> Result<(f32, f32, f32, f32)> {
@@ -139,6 +399,10 @@ fn main() -> Result<()> {
     let args = Args::parse();
     let start = Instant::now();
 
+    if let Some(Command::Cache { ref action }) = args.command {
+        return run_cache_command(action, &temp_dir(&args));
+    }
+
     // Configure rayon thread pool to use n-1 cores (leave one core free for OS)
     if args.parallel {
         let num_cpus = std::thread::available_parallelism()
@@ -183,58 +447,106 @@ fn main() -> Result<()> {
     // From here on, source is required (guaranteed by clap's required_unless_present)
     let source = args.source.as_ref().unwrap();
 
-    // Determine if source is a URL or local path
+    // Determine if source is a URL or local path. Bare repositories (local mirrors)
+    // have no files to copy, so they are cloned like remote repositories.
     let is_remote = source.starts_with("http://") 
         || source.starts_with("https://") 
         || source.starts_with("git@") 
-        || source.starts_with("ssh://");
+        || source.starts_with("ssh://")
+        || source.starts_with("file://")
+        || is_bare_repo(Path::new(&**source));
 
     // Setup temp directory
-    let temp_dir = args.temp_dir.clone().unwrap_or_else(|| {
-        std::env::temp_dir().join("git2pdf")
-    });
+    let temp_dir = temp_dir(&args);
     fs::create_dir_all(&temp_dir)?;
 
-    // Get source path (clone if remote, use directly if local)
-    let source_path = if is_remote {
+    let sparse_crates = if args.sparse {
+        match args.crates {
+            Some(ref filter) => Some(filter.split(',').map(|s| s.trim().to_string()).collect()),
+            None => bail!("--sparse requires --crates"),
+        }
+    } else {
+        None
+    };
+    let clone_options = CloneOptions {
+        depth: args.depth,
+        sparse_crates,
+        ssh_key: args.ssh_key.clone(),
+        offline: args.offline,
+    };
+
+    if args.staged && is_remote {
+        bail!("--staged only applies to local repositories with a working tree");
+    }
+
+    // Get source path (clone if remote, use directly if local). The lock on a cached
+    // clone is held until the end of the run.
+    let (source_path, _cache_lock, fresh_checkout) = if is_remote {
         let repo_name = extract_repo_name(&source)?;
-        let clone_path = temp_dir.join(&repo_name);
+        let cache = CloneCache::new(&temp_dir);
+        let clone_path = cache.path_for(source, &repo_name);
+        let cache_lock = cache.lock(&clone_path)?;
+        
+        // A clone that was interrupted is started over
+        if args.refresh || (clone_path.exists() && !cache.is_complete(&clone_path)) {
+            cache.remove(&clone_path)?;
+        }
+        if args.offline && !cache.is_complete(&clone_path) {
+            bail!("{} is not in the clone cache, run once without --offline", source);
+        }
         
         if args.verbose {
             println!("[{:?}] Cloning to: {}", start.elapsed(), clone_path.display());
         }
         
-        clone_or_open_repo(&source, &clone_path, args.verbose)?;
+        let fresh_checkout = clone_or_open_repo(&source, &clone_path, &clone_options, args.verbose)?;
+        cache.mark_used(&clone_path, source)?;
         
-        // Checkout the specified ref if provided
-        if let Some(ref git_ref) = args.r#ref {
-            if args.verbose {
-                println!("[{:?}] Checking out: {}", start.elapsed(), git_ref);
-            }
-            checkout_ref(&clone_path, git_ref, args.verbose)?;
-        }
-        
-        clone_path
+        (clone_path, Some(cache_lock), fresh_checkout)
     } else {
         let local_path = PathBuf::from(&*source);
         if !local_path.exists() {
             bail!("Repository path does not exist: {}", local_path.display());
         }
         
-        // Checkout the specified ref if provided (for local repos)
-        if let Some(ref git_ref) = args.r#ref {
+        (local_path, None, false)
+    };
+
+    if args.list_refs {
+        return print_refs(&source_path);
+    }
+
+    let single_ref = match args.r#ref.as_slice() {
+        [git_ref] => Some(git_ref.clone()),
+        _ => None,
+    };
+
+    // Check out the requested ref in a cloned repository, or the remote's default branch
+    // unless the clone just checked it out (a cached clone may be behind)
+    if is_remote && args.r#ref.len() <= 1 {
+        let git_ref = match single_ref {
+            Some(ref git_ref) => Some(git_ref.clone()),
+            None if !fresh_checkout => Some(default_branch(&source_path)?),
+            None => None,
+        };
+        if let Some(git_ref) = git_ref {
             if args.verbose {
                 println!("[{:?}] Checking out: {}", start.elapsed(), git_ref);
             }
-            checkout_ref(&local_path, git_ref, args.verbose)?;
+            match clone_options.sparse_crates {
+                Some(ref crates) => sparse_checkout(&source_path, &git_ref, crates, args.verbose)?,
+                None => {
+                    checkout_ref(&source_path, &git_ref, args.verbose)?;
+                }
+            }
         }
-        
-        local_path
-    };
+    }
 
     // Copy files to work directory (respecting .gitignore)
     // For remote repos, we already have them in temp_dir, so just use that
-    // For local repos, copy to temp to avoid modifying original
+    // For local repos, copy to temp to avoid modifying original, or export the
+    // requested ref from the repository (its worktree and HEAD stay untouched)
+    let mut exported = None;
     let work_dir = if is_remote {
         source_path.clone()
     } else {
@@ -243,68 +555,144 @@ fn main() -> Result<()> {
             .unwrap_or_else(|| "repo".to_string());
         let work_path = temp_dir.join(format!("{}-work", repo_name));
         
-        if args.verbose {
-            println!("[{:?}] Copying files to work directory: {}", start.elapsed(), work_path.display());
+        if let Some(ref git_ref) = single_ref {
+            if args.verbose {
+                println!("[{:?}] Exporting {} to work directory: {}", start.elapsed(), git_ref, work_path.display());
+            }
+            exported = Some(export_ref(&source_path, git_ref, &work_path, args.verbose)?);
+        } else if args.staged {
+            if args.verbose {
+                println!("[{:?}] Exporting staged files to work directory: {}", start.elapsed(), work_path.display());
+            }
+            export_index(&source_path, &work_path, args.verbose)?;
+        } else {
+            if args.verbose {
+                println!("[{:?}] Copying files to work directory: {}", start.elapsed(), work_path.display());
+            }
+            copy_repo_files(&source_path, &work_path, args.verbose)?;
         }
-        
-        copy_repo_files(&source_path, &work_path, args.verbose)?;
         work_path
     };
 
-    // Discover crates in the repository
-    if args.verbose {
-        println!("[{:?}] Discovering crates...", start.elapsed());
-    }
-    let crates = discover_crates(&work_dir)?;
-
-    // Run cargo fmt per-crate (unless disabled)
-    // We format per-crate instead of the whole workspace because submodule
-    // dependencies (e.g. webrender) may not be present in the work directory,
-    // which would cause `cargo fmt` on the root workspace to fail.
-    if !args.no_fmt {
-        if args.verbose {
-            println!("[{:?}] Running cargo fmt with line width {}...", start.elapsed(), args.line_width);
-        }
-        for c in &crates {
-            run_cargo_fmt(&c.path, args.line_width, args.verbose)?;
+    // Uncommitted changes of a local working tree (or index, with --staged), so the
+    // title pages don't claim that the printout matches the HEAD commit
+    let modified_files = if is_remote || !args.r#ref.is_empty() {
+        Vec::new()
+    } else {
+        match worktree_status(&source_path) {
+            Ok(status) if args.staged => status.staged,
+            Ok(status) => status.worktree_changes(),
+            Err(e) => {
+                eprintln!("Warning: Could not determine working tree status: {:#}", e);
+                Vec::new()
+            }
         }
-    }
-    
-    if crates.is_empty() {
-        bail!("No Rust crates found in repository");
-    }
+    };
 
-    if args.verbose {
-        println!("[{:?}] Found {} crate(s):", start.elapsed(), crates.len());
-        for c in &crates {
-            println!("  - {} ({})", c.name, c.path.display());
-        }
-    }
+    let repo_name = source_path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "repo".to_string());
 
-    // Filter crates if specified
-    let crates_to_process: Vec<&CrateInfo> = if let Some(ref filter) = args.crates {
-        let filter_names: Vec<&str> = filter.split(',').map(|s| s.trim()).collect();
-        crates.iter()
-            .filter(|c| filter_names.contains(&c.name.as_str()))
-            .collect()
-    } else {
-        crates.iter().collect()
+    // Submodules: fetch the missing ones if requested, otherwise list them on the title pages
+    let submodules = match list_submodules(&source_path) {
+        Ok(submodules) => submodules,
+        Err(e) => {
+            if args.verbose {
+                println!("Warning: Could not read submodules: {}", e);
+            }
+            Vec::new()
+        }
+    };
+    let submodule_mirrors = temp_dir.join(format!("{}-submodules", repo_name));
+    let fill_submodules = |submodules: &[SubmoduleInfo], work_dir: &Path| {
+        populate_submodules(submodules, work_dir, &submodule_mirrors, args.submodules.then_some(&clone_options), args.verbose)
     };
 
-    if crates_to_process.is_empty() {
-        bail!("No crates matched the filter");
+    // Versions of the repository to print: the work directory itself, or one
+    // exported directory per ref when several refs are compared
+    let mut targets: Vec<RefTarget> = Vec::new();
+    if args.r#ref.len() > 1 {
+        if args.blame || args.blame_colors || args.since.is_some() {
+            bail!("--blame and --since can't be combined with multiple --ref values");
+        }
+        for git_ref in &args.r#ref {
+            let export_path = temp_dir.join(format!("{}-ref-{}", repo_name, sanitize_file_name(git_ref)));
+            if args.verbose {
+                println!("[{:?}] Exporting {} to: {}", start.elapsed(), git_ref, export_path.display());
+            }
+            let export = export_ref(&source_path, git_ref, &export_path, args.verbose)?;
+            // Exports contain no submodules: fetch them at the commits recorded in this ref
+            let skipped_submodules = fill_submodules(&export.submodules(&submodules), &export_path);
+            targets.push(RefTarget {
+                label: Some(git_ref.clone()),
+                work_dir: export_path,
+                git_hash: Some(export.hash),
+                skipped_submodules,
+                skipped_symlinks: export.symlinks,
+                modified_files: Vec::new(),
+            });
+        }
+    } else {
+        let skipped_submodules = match exported {
+            Some(ref export) => fill_submodules(&export.submodules(&submodules), &work_dir),
+            None => fill_submodules(&submodules, &work_dir),
+        };
+        let (git_hash, skipped_symlinks) = match exported {
+            Some(export) => (Some(export.hash), export.symlinks),
+            None => (get_git_hash(&source_path).ok(), Vec::new()),
+        };
+        targets.push(RefTarget {
+            label: None,
+            git_hash,
+            work_dir: work_dir.clone(),
+            skipped_submodules,
+            skipped_symlinks,
+            modified_files,
+        });
     }
 
     // Create output directory
     fs::create_dir_all(&args.output)?;
 
+    // Files changed since the baseline ref, for change bars
+    let baseline = match args.since {
+        Some(ref base) => {
+            if args.verbose {
+                println!("[{:?}] Diffing against baseline: {}", start.elapsed(), base);
+            }
+            // Compare what is printed: an exported --ref, or HEAD with the local edits
+            let printed_ref = if is_remote { None } else { single_ref.as_deref() };
+            Some(changes_since(&source_path, base, printed_ref, &targets[0].modified_files)?)
+        }
+        None => None,
+    };
+
+    // Commit log for the appendix, computed once and filtered per crate
+    let commit_range = args.commit_log.as_deref().map(parse_commit_range).transpose()?;
+    let commits = match commit_range {
+        Some((ref from, ref to)) => {
+            if args.verbose {
+                println!("[{:?}] Collecting commit log {}..{}", start.elapsed(), from, to);
+            }
+            commit_log(&source_path, from, to)?
+        }
+        None => Vec::new(),
+    };
+
+    // Blame opens the repository once and shares it between the rendering threads. An
+    // exported ref is blamed at that ref; a clone checked out at a ref has it as HEAD.
+    let blame_repo = if args.blame || args.blame_colors {
+        let blame_ref = if is_remote { None } else { single_ref.as_deref() };
+        Some(open_blame_repo(&source_path, blame_ref)?)
+    } else {
+        None
+    };
+
     // Load syntax highlighting
     if args.verbose {
         println!("[{:?}] Loading syntax highlighting...", start.elapsed());
     }
 
-    // Get git hash for title pages
-    let git_hash = get_git_hash(&work_dir).ok();
 
     // Load font bytes once (shared across all parallel tasks)
     let font_bytes: Arc<Vec<u8>> = Arc::new(if let Some(ref font_path) = args.font {
@@ -330,173 +718,844 @@ fn main() -> Result<()> {
         ..Default::default()
     };
 
-    // Process each crate
-    for crate_info in crates_to_process {
-        if args.verbose {
-            println!("\n[{:?}] Processing crate: {}", start.elapsed(), crate_info.name);
+    // Name of the combined printout (--combine)
+    let workspace_name = if is_remote {
+        extract_repo_name(source)?
+    } else {
+        fs::canonicalize(&source_path)?
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "workspace".to_string())
+    };
+    // Public API printouts don't overwrite the full ones
+    let api_suffix = if args.public_api { "-api" } else { "" };
+
+    // Per-crate statistics for each ref, for the comparison summary
+    let mut comparison: BTreeMap<String, BTreeMap<String, CrateStats>> = BTreeMap::new();
+
+    for target in &targets {
+        if let Some(ref label) = target.label {
+            println!("\nRef: {}", label);
         }
 
-        // Classify files
-        let files = classify_files(&crate_info.path, args.include_tests)?;
-        
-        let source_files: Vec<SourceFile> = files.into_iter()
-            .filter(|f| f.category == FileCategory::Source || 
-                       (args.include_tests && matches!(f.category, FileCategory::Test | FileCategory::IntegrationTest)))
-            .collect();
+        // Discover crates in the repository
+        if args.verbose {
+            println!("[{:?}] Discovering crates...", start.elapsed());
+        }
+        let discovery = if args.cargo_metadata {
+            discover_crates_with_metadata(&target.work_dir, &args.skip)?
+        } else {
+            discover_crates(&target.work_dir, &args.skip)?
+        };
+        for warning in &discovery.warnings {
+            eprintln!("Warning: {}", warning);
+        }
+        let crates = discovery.crates;
 
-        if source_files.is_empty() {
-            if args.verbose {
-                println!("  No source files found, skipping");
+        if crates.is_empty() {
+            if discovery.root == RootManifest::VirtualWorkspace {
+                bail!("The workspace in the root Cargo.toml has no member crates");
             }
-            continue;
+            if !discovery.skipped.is_empty() {
+                bail!(
+                    "No Rust crates found in repository ({} skipped, see --skip)",
+                    discovery.skipped.len()
+                );
+            }
+            bail!("No Rust crates found in repository");
         }
 
         if args.verbose {
-            println!("  Found {} source file(s), processing in parallel...", source_files.len());
+            let layout = match discovery.root {
+                RootManifest::None => "no root Cargo.toml",
+                RootManifest::Package => "single package",
+                RootManifest::Workspace => "workspace with a root package",
+                RootManifest::VirtualWorkspace => "virtual workspace",
+            };
+            println!("[{:?}] Found {} crate(s) ({}):", start.elapsed(), crates.len(), layout);
+            for c in &crates {
+                let default = if c.is_default_member { "" } else { ", not a default member" };
+                println!("  - {} ({}, edition {}{})", c.name, c.path.display(), c.edition, default);
+                println!("      included: {}", c.inclusion);
+                if !c.targets.is_empty() {
+                    let targets: Vec<String> = c.targets.iter()
+                        .map(|t| format!("{:?} {}", t.kind, t.name).to_lowercase())
+                        .collect();
+                    println!("      targets: {}", targets.join(", "));
+                }
+                if !c.features.is_empty() {
+                    println!("      features: {}", c.features.join(", "));
+                }
+            }
+            if !discovery.skipped.is_empty() {
+                println!("[{:?}] Skipped {} crate(s):", start.elapsed(), discovery.skipped.len());
+                for (path, reason) in &discovery.skipped {
+                    println!("  - {} ({})", path, reason);
+                }
+            }
         }
 
-        // Create fonts map for PDF generation
-        let mut fonts: BTreeMap<String, Base64OrRaw> = BTreeMap::new();
-        fonts.insert("RobotoMono".to_string(), Base64OrRaw::Raw((*font_bytes).clone()));
-        
-        // Build font pool ONCE and share across all from_html calls.
-        let fc_cache_start = Instant::now();
-        let raw_fonts: BTreeMap<String, Vec<u8>> = fonts.iter().map(|(k, v)| {
-            let bytes = match v {
-                Base64OrRaw::Raw(b) => b.clone(),
-                Base64OrRaw::B64(_) => Vec::new(),
-            };
-            (k.clone(), bytes)
-        }).collect();
-        let font_pool = printpdf::html::build_font_pool(
-            &raw_fonts,
-            Some(&["monospace"]),
-        );
-        if args.verbose {
-            println!("  Font pool built in {:?} (shared across all files)", fc_cache_start.elapsed());
+        // Filter crates if specified, adding their dependencies with --with-deps.
+        // Exclusions ("!name") also apply to the added dependencies.
+        let filter = args.crates.as_deref().map(CrateFilter::parse).transpose()?;
+        if let Some(ref filter) = filter {
+            for spec in filter.unmatched(&crates, &target.work_dir) {
+                eprintln!("Warning: --crates pattern '{}' matches no crate", spec);
+            }
+        }
+        let all_crates: Vec<&CrateInfo> = crates.iter().collect();
+        let selected: Vec<usize> = (0..crates.len())
+            .filter(|&i| !args.default_members || crates[i].is_default_member)
+            .filter(|&i| filter.as_ref().is_none_or(|f| f.includes(&crates[i], &target.work_dir)))
+            .collect();
+        let selected = if args.with_deps {
+            DependencyGraph::new(&all_crates).with_dependencies(&selected)
+        } else {
+            selected
+        };
+        let crates_to_process: Vec<&CrateInfo> = selected.into_iter()
+            .map(|i| &crates[i])
+            .filter(|c| !filter.as_ref().is_some_and(|f| f.excludes(c, &target.work_dir)))
+            .collect();
+
+        if crates_to_process.is_empty() {
+            let mut names: Vec<&str> = crates.iter().map(|c| c.name.as_str()).collect();
+            names.sort_unstable();
+            bail!(
+                "No crates matched {}. Available crates: {}",
+                args.crates.as_deref().map_or("--default-members".to_string(), |c| format!("--crates '{}'", c)),
+                names.join(", ")
+            );
         }
 
-        // Phase 1: Render each source file to an individual PDF on disk.
-        // This avoids holding all PdfDocuments in memory at once (OOM on large crates).
-        let theme_name = args.theme.clone();
-        let font_size = args.font_size;
-        let pdf_opts = pdf_options.clone();
-        let font_bytes_clone = Arc::clone(&font_bytes);
-        let syntax_set_clone = Arc::clone(&syntax_set);
-        let theme_set_clone = Arc::clone(&theme_set);
-        let font_pool_clone = font_pool.clone();
+        // Print crates after the crates they depend on
+        let graph = DependencyGraph::new(&crates_to_process);
+        let crates_to_process: Vec<&CrateInfo> = graph.topological_order().into_iter()
+            .map(|i| graph.crates()[i])
+            .collect();
+
+        // Images outside of every crate directory (e.g. docs/ at the repository root) belong
+        // to the whole repository: they follow the workspace overview with --combine, and
+        // every crate's own figures otherwise
+        let repo_images = if !args.images || crates.iter().any(|c| c.path == target.work_dir) {
+            Vec::new()
+        } else {
+            let crate_dirs: Vec<PathBuf> = crates.iter().map(|c| c.path.clone()).collect();
+            find_image_files(&target.work_dir, &crate_dirs)?
+        };
+
+        // With --combine, all crates go into one document after the workspace overview page
+        let mut workspace_doc = if args.combine {
+            let title = match target.label {
+                Some(ref label) => format!("{} ({})", workspace_name, label),
+                None => workspace_name.clone(),
+            };
+            let mut images: BTreeMap<String, Base64OrRaw> = BTreeMap::new();
+            let diagram_key = graph.has_edges().then(|| {
+                images.insert("dependency-graph.svg".to_string(), Base64OrRaw::Raw(graph.render_svg().into_bytes()));
+                "dependency-graph.svg"
+            });
+            let overview_html = generate_dependency_graph_html(&title, &graph, diagram_key, args.font_size);
+            let mut fonts: BTreeMap<String, Base64OrRaw> = BTreeMap::new();
+            fonts.insert("RobotoMono".to_string(), Base64OrRaw::Raw((*font_bytes).clone()));
+            let mut overview_doc = PdfDocument::from_html_with_cache(
+                &overview_html, &images, &fonts, &pdf_options, &mut Vec::new(), None,
+            ).map_err(|e| anyhow::anyhow!("Failed to generate workspace overview: {}", e))?;
+            if !repo_images.is_empty() {
+                let mut report = RenderReport::default();
+                let (images, figures) = load_figures(&repo_images, &mut report);
+                if !figures.is_empty() {
+                    let figures_html = generate_figures_html(&title, &figures, args.font_size);
+                    match PdfDocument::from_html_with_cache(
+                        &figures_html, &images, &fonts, &pdf_options, &mut Vec::new(), None,
+                    ) {
+                        Ok(figures_doc) => overview_doc.append_document(figures_doc),
+                        Err(e) => report_unrendered_figures(&mut report, figures, &e),
+                    }
+                }
+                report.print(&workspace_name);
+            }
+            Some(overview_doc)
+        } else {
+            None
+        };
+
+        // Statistics of the crates in the combined printout, for --json
+        let mut workspace_summaries: Vec<CrateSummary> = Vec::new();
 
-        let cache_dir = temp_dir.join(format!("{}-cache", crate_info.name));
-        fs::create_dir_all(&cache_dir)?;
+        // Process each crate
+        for crate_info in crates_to_process {
+            if args.verbose {
+                println!("\n[{:?}] Processing crate: {}", start.elapsed(), crate_info.name);
+            }
 
-        let process_file = |file: &SourceFile| -> Result<(String, PathBuf, usize, std::time::Duration)> {
-            let file_start = std::time::Instant::now();
-            let theme: Option<&Theme> = if theme_name.to_lowercase() == "none" {
+            // Crate directory relative to the repository root ("" for the root), as used in git paths
+            let crate_prefix = crate_info.path.strip_prefix(&target.work_dir)
+                .map(|p| p.components()
+                    .map(|c| c.as_os_str().to_string_lossy().to_string())
+                    .collect::<Vec<_>>()
+                    .join("/"))
+                .unwrap_or_default();
+
+            // Classify files
+            let files = classify_files(&crate_info.path, &crate_info.targets, args.include_tests, args.hex_dump)?;
+
+            let source_files: Vec<SourceFile> = files.into_iter()
+                .filter(|f| f.category == FileCategory::Source || 
+                           (args.include_tests && matches!(f.category, FileCategory::Test | FileCategory::IntegrationTest)))
+                .collect();
+
+            if source_files.is_empty() {
+                if args.verbose {
+                    println!("  No source files found, skipping");
+                }
+                continue;
+            }
+
+            if args.verbose {
+                println!("  Found {} source file(s), processing in parallel...", source_files.len());
+            }
+
+            // Create fonts map for PDF generation
+            let mut fonts: BTreeMap<String, Base64OrRaw> = BTreeMap::new();
+            fonts.insert("RobotoMono".to_string(), Base64OrRaw::Raw((*font_bytes).clone()));
+
+            // Build font pool ONCE and share across all from_html calls.
+            let fc_cache_start = Instant::now();
+            let raw_fonts: BTreeMap<String, Vec<u8>> = fonts.iter().map(|(k, v)| {
+                let bytes = match v {
+                    Base64OrRaw::Raw(b) => b.clone(),
+                    Base64OrRaw::B64(_) => Vec::new(),
+                };
+                (k.clone(), bytes)
+            }).collect();
+            let font_pool = printpdf::html::build_font_pool(
+                &raw_fonts,
+                Some(&["monospace"]),
+            );
+            if args.verbose {
+                println!("  Font pool built in {:?} (shared across all files)", fc_cache_start.elapsed());
+            }
+
+            // Phase 1: Render each source file to an individual PDF on disk.
+            // This avoids holding all PdfDocuments in memory at once (OOM on large crates).
+            let theme_name = args.theme.clone();
+            let font_size = args.font_size;
+            // Blame line numbers refer to the committed file, so blamed files are printed unformatted
+            let blame_enabled = args.blame || args.blame_colors;
+            // The public API is generated token by token, it is unreadable unformatted
+            let format_width = if args.public_api {
+                Some(args.line_width)
+            } else if args.no_fmt || blame_enabled {
                 None
             } else {
-                theme_set_clone.themes.get(&theme_name)
-                    .or_else(|| theme_set_clone.themes.get("InspiredGitHub"))
+                Some(args.line_width)
+            };
+            let public_api = args.public_api;
+            let index = args.index || args.links;
+
+            // Name resolution for --links needs the definitions of the whole crate up front
+            let symbols = args.links.then(|| {
+                let items: Vec<IndexedItem> = source_files.iter()
+                    .filter(|f| f.path.extension().is_some_and(|e| e == "rs"))
+                    .filter_map(|f| fs::read_to_string(&f.path).ok())
+                    .flat_map(|content| index_items(&content).unwrap_or_default())
+                    .collect();
+                SymbolTable::new(&items)
+            });
+            let hex_dump_limit = args.hex_dump;
+            let pdf_opts = pdf_options.clone();
+            let font_bytes_clone = Arc::clone(&font_bytes);
+            let syntax_set_clone = Arc::clone(&syntax_set);
+            let theme_set_clone = Arc::clone(&theme_set);
+            let font_pool_clone = font_pool.clone();
+
+            let cache_dir = temp_dir.join(format!("{}-cache", crate_info.name));
+            fs::create_dir_all(&cache_dir)?;
+
+            // Ok(None): a file without public items in --public-api mode
+            let process_file = |file: &SourceFile| -> std::result::Result<Option<CachedFile>, FileIssue> {
+                let file_start = std::time::Instant::now();
+                let rel_path = file.relative_path.to_string_lossy().to_string();
+                let render_failed = |e: String| FileIssue::new(rel_path.clone(), IssueKind::RenderFailed, e);
+                let mut issues = Vec::new();
+                let theme: Option<&Theme> = if theme_name.to_lowercase() == "none" {
+                    None
+                } else {
+                    theme_set_clone.themes.get(&theme_name)
+                        .or_else(|| theme_set_clone.themes.get("InspiredGitHub"))
+                };
+
+                let loaded = load_file(&file.path)
+                    .map_err(|e| render_failed(format!("Failed to read file: {}", e)))?;
+
+                let html_start = std::time::Instant::now();
+                let mut items = Vec::new();
+                let (html, stats) = match loaded {
+                    FileContent::Text { content: original, decoded_from } => {
+                        if let Some(encoding) = decoded_from {
+                            issues.push(FileIssue::new(rel_path.clone(), IssueKind::NonUtf8, format!("decoded as {}", encoding)));
+                        }
+
+                        // Reduce Rust files to their public items, leaving out files without any
+                        let original = if public_api && file.path.extension().is_some_and(|e| e == "rs") {
+                            match public_api::public_api(&original) {
+                                Ok(api) if api.is_empty() => return Ok(None),
+                                Ok(api) => api,
+                                Err(e) => {
+                                    issues.push(FileIssue::new(rel_path.clone(), IssueKind::ParseFailed, format!("{:#}", e)));
+                                    original
+                                }
+                            }
+                        } else {
+                            original
+                        };
+
+                        // Format the in-memory copy only; the file on disk is never touched.
+                        // rustfmt only understands Rust, other text files are printed as they are.
+                        let format_width = format_width.filter(|_| file.path.extension().is_some_and(|e| e == "rs"));
+                        let content = match format_width {
+                            Some(width) => {
+                                let (formatted, fmt_error) = format_or_original(&original, width, &crate_info.edition, &crate_info.path);
+                                if let Some(e) = fmt_error {
+                                    issues.push(FileIssue::new(rel_path.clone(), IssueKind::FormatFailed, e));
+                                }
+                                formatted
+                            }
+                            None => original,
+                        };
+
+                        // Path relative to the repository, for blame and baseline lookups
+                        let repo_rel_path = file.path.strip_prefix(&target.work_dir).unwrap_or(&file.path);
+                        let git_path = repo_rel_path.components()
+                            .map(|c| c.as_os_str().to_string_lossy())
+                            .collect::<Vec<_>>()
+                            .join("/");
+                        let mut annotations = match blame_repo {
+                            // Blame describes the commit; on a modified file its lines would be shifted
+                            Some(_) if target.modified_files.contains(&git_path) => {
+                                issues.push(FileIssue::new(rel_path.clone(), IssueKind::BlameFailed, "uncommitted changes".to_string()));
+                                Vec::new()
+                            }
+                            Some(ref repo) => match blame_file(repo, &source_path, repo_rel_path) {
+                                Ok(blame) => blame_annotations(&blame, args.blame_colors),
+                                Err(e) => {
+                                    issues.push(FileIssue::new(rel_path.clone(), IssueKind::BlameFailed, e.to_string()));
+                                    Vec::new()
+                                }
+                            },
+                            None => Vec::new(),
+                        };
+
+                        if let Some(changed) = baseline.as_ref().and_then(|b| b.get(repo_rel_path)) {
+                            // Compare like with like: format the baseline the same way as the rendered file
+                            let changes = match (changed, format_width) {
+                                (Some(base), Some(width)) => line_changes(&format_or_original(base, width, &crate_info.edition, &crate_info.path).0, &content),
+                                (Some(base), None) => line_changes(base, &content),
+                                (None, _) => vec![LineChange::Added; content.lines().count()],
+                            };
+                            if annotations.len() < changes.len() {
+                                annotations.resize(changes.len(), LineAnnotation::default());
+                            }
+                            for (annotation, change) in annotations.iter_mut().zip(changes) {
+                                annotation.change = Some(change);
+                            }
+                        }
+
+                        // Index the printed (formatted) text so line numbers match; files syn
+                        // can't parse are left out of the index
+                        if index && file.path.extension().is_some_and(|e| e == "rs") {
+                            items = index_items(&content).unwrap_or_default();
+                            // Anchor the definitions to read back the pages they land on
+                            if annotations.len() < content.lines().count() {
+                                annotations.resize(content.lines().count(), LineAnnotation::default());
+                            }
+                            for item in &items {
+                                if let Some(annotation) = annotations.get_mut(item.line.wrapping_sub(1)) {
+                                    annotation.anchor = true;
+                                }
+                            }
+                            if let Some(ref symbols) = symbols {
+                                let links = symbols.links(&content);
+                                if annotations.len() < links.len() {
+                                    annotations.resize(links.len(), LineAnnotation::default());
+                                }
+                                for (annotation, links) in annotations.iter_mut().zip(links) {
+                                    annotation.links = links;
+                                }
+                            }
+                        }
+
+                        let mut highlight_warnings = Vec::new();
+                        let html = generate_html_for_single_file(
+                            file, &content, &syntax_set_clone, theme, font_size, &annotations, &mut highlight_warnings,
+                        )
+                            .map_err(|e| render_failed(e.to_string()))?;
+                        if let Some(first) = highlight_warnings.first() {
+                            issues.push(FileIssue::new(
+                                rel_path.clone(),
+                                IssueKind::HighlightFailed,
+                                format!("{} line(s), first: {}", highlight_warnings.len(), first),
+                            ));
+                        }
+                        (html, FileStats::new(&content, file.path.extension().is_some_and(|e| e == "rs")))
+                    }
+                    FileContent::Binary { bytes } => {
+                        let size = format_size(bytes.len() as u64);
+                        let dump = hex_dump_limit
+                            .filter(|&limit| bytes.len() as u64 <= limit)
+                            .map(|_| hex_dump(&bytes));
+                        issues.push(FileIssue::new(rel_path.clone(), IssueKind::Binary, size.clone()));
+                        (generate_html_for_binary_file(file, &size, dump.as_deref(), font_size), FileStats::default())
+                    }
+                };
+                let html_elapsed = html_start.elapsed();
+
+                let mut file_fonts: BTreeMap<String, Base64OrRaw> = BTreeMap::new();
+                file_fonts.insert("RobotoMono".to_string(), Base64OrRaw::Raw((*font_bytes_clone).clone()));
+
+                let pdf_start = std::time::Instant::now();
+                let mut warnings = Vec::new();
+                let mut doc = PdfDocument::from_html_with_cache(
+                    &html, &BTreeMap::new(), &file_fonts, &pdf_opts, &mut warnings,
+                    Some(font_pool_clone.clone()),
+                ).map_err(|e| render_failed(format!("Failed to generate PDF: {}", e)))?;
+                let pdf_elapsed = pdf_start.elapsed();
+                let item_pages = if items.is_empty() { HashMap::new() } else { anchored_line_pages(&mut doc) };
+                let pages = doc.page_count();
+
+                // Save to disk immediately, then drop to free memory
+                let safe_name = file.relative_path.to_string_lossy()
+                    .replace('/', "__")
+                    .replace('\\', "__");
+                let cache_path = cache_dir.join(format!("{}.pdf", safe_name));
+                {
+                    let save_options = PdfSaveOptions::default();
+                    let mut save_warnings = Vec::new();
+                    let bytes = doc.save(&save_options, &mut save_warnings);
+                    fs::write(&cache_path, bytes)
+                        .map_err(|e| render_failed(format!("Failed to write cached PDF: {}", e)))?;
+                }
+
+                eprintln!("    [detail] {} ({} LOC, {} bytes HTML): html_gen={:.1?}, pdf_render={:.1?}",
+                    file.relative_path.display(), stats.lines, html.len(), html_elapsed, pdf_elapsed);
+
+                Ok(Some(CachedFile {
+                    path: rel_path,
+                    cache_path,
+                    section: file.section.clone(),
+                    stats,
+                    pages,
+                    items,
+                    item_pages,
+                    elapsed: file_start.elapsed(),
+                    issues,
+                }))
             };
 
-            let loc = std::fs::read_to_string(&file.path)
-                .map(|s| s.lines().count())
-                .unwrap_or(0);
-
-            let html_start = std::time::Instant::now();
-            let html = generate_html_for_single_file(file, &syntax_set_clone, theme, font_size)?;
-            let html_elapsed = html_start.elapsed();
-
-            let mut file_fonts: BTreeMap<String, Base64OrRaw> = BTreeMap::new();
-            file_fonts.insert("RobotoMono".to_string(), Base64OrRaw::Raw((*font_bytes_clone).clone()));
-
-            let pdf_start = std::time::Instant::now();
-            let mut warnings = Vec::new();
-            let doc = PdfDocument::from_html_with_cache(
-                &html, &BTreeMap::new(), &file_fonts, &pdf_opts, &mut warnings,
-                Some(font_pool_clone.clone()),
-            ).map_err(|e| anyhow::anyhow!("Failed to generate PDF for {}: {}", file.relative_path.display(), e))?;
-            let pdf_elapsed = pdf_start.elapsed();
-
-            // Save to disk immediately, then drop to free memory
-            let safe_name = file.relative_path.to_string_lossy()
-                .replace('/', "__")
-                .replace('\\', "__");
-            let cache_path = cache_dir.join(format!("{}.pdf", safe_name));
-            {
-                let save_options = PdfSaveOptions::default();
-                let mut save_warnings = Vec::new();
-                let bytes = doc.save(&save_options, &mut save_warnings);
-                fs::write(&cache_path, bytes)?;
-            }
+            let file_results: Vec<std::result::Result<Option<CachedFile>, FileIssue>> = if args.parallel {
+                use rayon::prelude::*;
+                source_files.par_iter().map(process_file).collect()
+            } else {
+                source_files.iter().map(process_file).collect()
+            };
 
-            eprintln!("    [detail] {} ({} LOC, {} bytes HTML): html_gen={:.1?}, pdf_render={:.1?}",
-                file.relative_path.display(), loc, html.len(), html_elapsed, pdf_elapsed);
+            // Collect successful results (preserving source file order)
+            let mut report = RenderReport::default();
+            for symlink in &target.skipped_symlinks {
+                let in_crate = if crate_prefix.is_empty() {
+                    Some(symlink.as_str())
+                } else {
+                    symlink.strip_prefix(crate_prefix.as_str()).and_then(|rest| rest.strip_prefix('/'))
+                };
+                if let Some(path) = in_crate {
+                    report.push(FileIssue::new(path.to_string(), IssueKind::Symlink, "git exports contain regular files only".to_string()));
+                }
+            }
+            let mut cached_files: Vec<CachedFile> = Vec::new();
+            for result in file_results {
+                match result {
+                    Ok(Some(mut cached)) => {
+                        report.extend(cached.issues.drain(..));
+                        cached_files.push(cached);
+                    }
+                    Ok(None) => {}
+                    Err(issue) => report.push(issue),
+                }
+            }
 
-            Ok((file.relative_path.to_string_lossy().to_string(), cache_path, loc, file_start.elapsed()))
-        };
+            // Phase 2: Generate title page in-memory, then append each cached file PDF one by one.
+            // The commit is marked "+dirty" if files of this crate have uncommitted changes.
+            let crate_modified: Vec<String> = target.modified_files.iter()
+                .filter_map(|f| if crate_prefix.is_empty() {
+                    Some(f.clone())
+                } else {
+                    f.strip_prefix(crate_prefix.as_str())
+                        .and_then(|rest| rest.strip_prefix('/'))
+                        .map(str::to_string)
+                })
+                .collect();
+            let git_hash = target.git_hash.as_ref().map(|hash| if crate_modified.is_empty() {
+                hash.clone()
+            } else {
+                format!("{}+dirty", hash)
+            });
+            let title_html = generate_title_page_html(
+                crate_info, git_hash.as_deref(), &target.skipped_submodules, &crate_modified, args.public_api,
+                args.font_size,
+            );
+            let mut title_warnings = Vec::new();
+            let mut combined_doc = PdfDocument::from_html_with_cache(
+                &title_html, &BTreeMap::new(), &fonts, &pdf_options, &mut title_warnings,
+                Some(font_pool.clone()),
+            ).map_err(|e| anyhow::anyhow!("Failed to generate title page: {}", e))?;
 
-        let file_results: Vec<Result<(String, PathBuf, usize, std::time::Duration)>> = if args.parallel {
-            use rayon::prelude::*;
-            source_files.par_iter().map(process_file).collect()
-        } else {
-            source_files.iter().map(process_file).collect()
-        };
+            if args.verbose {
+                println!("  Title page: {} page(s). Appending {} file PDFs...", combined_doc.page_count(), cached_files.len());
+            }
 
-        // Collect successful results (preserving source file order)
-        let mut cached_files: Vec<(String, PathBuf, usize, std::time::Duration)> = Vec::new();
-        for result in file_results {
-            match result {
-                Ok(info) => cached_files.push(info),
-                Err(e) => eprintln!("  Warning: {}", e),
+            // Pages before this crate's title page in the combined workspace printout
+            let page_offset = workspace_doc.as_ref().map_or(0, |doc| doc.page_count());
+
+            // Each binary target starts with a divider page. Dividers are rendered up front
+            // so the summary page can list the pages of every file.
+            let mut dividers = Vec::with_capacity(cached_files.len());
+            let mut section = None;
+            for cached in &cached_files {
+                let mut divider = None;
+                if cached.section != section {
+                    section = cached.section.clone();
+                    let target = crate_info.targets.iter()
+                        .find(|t| t.kind == TargetKind::Bin && Some(&t.name) == section.as_ref());
+                    if let Some(target) = target {
+                        let section_html = generate_section_page_html(crate_info, target, args.font_size);
+                        let section_doc = PdfDocument::from_html_with_cache(
+                            &section_html, &BTreeMap::new(), &fonts, &pdf_options, &mut Vec::new(),
+                            Some(font_pool.clone()),
+                        ).map_err(|e| anyhow::anyhow!("Failed to generate section page for {}: {}", target.name, e))?;
+                        divider = Some(section_doc);
+                    }
+                }
+                dividers.push(divider);
             }
-        }
 
-        // Phase 2: Generate title page in-memory, then append each cached file PDF one by one.
-        let title_html = generate_title_page_html(crate_info, git_hash.as_deref(), args.font_size);
-        let mut title_warnings = Vec::new();
-        let mut combined_doc = PdfDocument::from_html_with_cache(
-            &title_html, &BTreeMap::new(), &fonts, &pdf_options, &mut title_warnings,
-            Some(font_pool.clone()),
-        ).map_err(|e| anyhow::anyhow!("Failed to generate title page: {}", e))?;
+            // Summary of the files on the pages they will be printed on, from `first_page` on
+            let file_summaries = |first_page: usize| -> Vec<FileSummary> {
+                let mut page = first_page;
+                cached_files.iter().zip(&dividers)
+                    .map(|(cached, divider)| {
+                        page += divider.as_ref().map_or(0, |doc| doc.page_count());
+                        let summary = FileSummary {
+                            path: cached.path.clone(),
+                            stats: cached.stats,
+                            first_page: page,
+                            pages: cached.pages,
+                        };
+                        page += cached.pages;
+                        summary
+                    })
+                    .collect()
+            };
+            if args.summary {
+                // The summary's own length moves the files after it: render it again until
+                // the page numbers it lists are the ones it ends up on
+                let mut summary_pages = 0;
+                let mut summary_doc = None;
+                for _ in 0..3 {
+                    let files = file_summaries(page_offset + combined_doc.page_count() + summary_pages + 1);
+                    let summary_html = generate_summary_page_html(
+                        crate_info, &CrateSummary::new(&crate_info.name, &crate_info.version, files), args.font_size,
+                    );
+                    let doc = PdfDocument::from_html_with_cache(
+                        &summary_html, &BTreeMap::new(), &fonts, &pdf_options, &mut Vec::new(),
+                        Some(font_pool.clone()),
+                    ).map_err(|e| anyhow::anyhow!("Failed to generate summary page: {}", e))?;
+                    let settled = doc.page_count() == summary_pages;
+                    summary_pages = doc.page_count();
+                    summary_doc = Some(doc);
+                    if settled {
+                        break;
+                    }
+                }
+                if let Some(doc) = summary_doc {
+                    combined_doc.append_document(doc);
+                }
+            }
 
-        if args.verbose {
-            println!("  Title page: {} page(s). Appending {} file PDFs...", combined_doc.page_count(), cached_files.len());
-        }
+            let mut index_entries: Vec<IndexEntry> = Vec::new();
+            // Items with their page in this crate's document; links are resolved on that
+            // document before it is added to a combined workspace printout
+            let mut definition_pages: Vec<(&IndexedItem, Option<usize>)> = Vec::new();
+            let mut printed_files = Vec::with_capacity(cached_files.len());
+            let mut file_count = 0;
+            for (cached, divider) in cached_files.iter().zip(dividers) {
+                if let Some(divider) = divider {
+                    combined_doc.append_document(divider);
+                }
+                let file_bytes = fs::read(&cached.cache_path)?;
+                let file_doc = PdfDocument::parse(
+                    &file_bytes, &PdfParseOptions::default(), &mut Vec::new(),
+                ).map_err(|e| anyhow::anyhow!("Failed to reload {}: {}", cached.path, e))?;
+                drop(file_bytes);
+                let local_first_page = combined_doc.page_count() + 1;
+                let first_page = page_offset + local_first_page;
+                let pages = file_doc.page_count();
+                for item in &cached.items {
+                    let page = cached.item_pages.get(&item.line).copied();
+                    definition_pages.push((item, page.map(|page| local_first_page + page)));
+                    if args.index {
+                        index_entries.push(IndexEntry {
+                            item: item.clone(),
+                            path: cached.path.clone(),
+                            page: page.map(|page| first_page + page),
+                        });
+                    }
+                }
+                printed_files.push(FileSummary {
+                    path: cached.path.clone(),
+                    stats: cached.stats,
+                    first_page,
+                    pages,
+                });
+                combined_doc.append_document(file_doc);
+                file_count += 1;
+                if args.verbose {
+                    println!("  Added: {} ({} LOC, {} pages total, {:.1?})", cached.path, cached.stats.lines, combined_doc.page_count(), cached.elapsed);
+                }
+            }
 
-        let mut file_count = 0;
-        for (path, cache_path, loc, elapsed) in &cached_files {
-            let file_bytes = fs::read(cache_path)?;
-            let file_doc = PdfDocument::parse(
-                &file_bytes, &PdfParseOptions::default(), &mut Vec::new(),
-            ).map_err(|e| anyhow::anyhow!("Failed to reload {}: {}", path, e))?;
-            drop(file_bytes);
-            combined_doc.append_document(file_doc);
-            file_count += 1;
             if args.verbose {
-                println!("  Added: {} ({} LOC, {} pages total, {:.1?})", path, loc, combined_doc.page_count(), elapsed);
+                println!("  Combined {} files into {} pages", file_count, combined_doc.page_count());
             }
+
+            // Append images (diagrams etc.) as figures after the source
+            if args.images {
+                let nested_crates: Vec<PathBuf> = crates.iter()
+                    .filter(|c| c.path != crate_info.path && c.path.starts_with(&crate_info.path))
+                    .map(|c| c.path.clone())
+                    .collect();
+                let mut image_files = find_image_files(&crate_info.path, &nested_crates)?;
+                if workspace_doc.is_none() {
+                    image_files.extend(repo_images.iter().cloned());
+                }
+                let (images, figures) = load_figures(&image_files, &mut report);
+
+                if !figures.is_empty() {
+                    if args.verbose {
+                        println!("  Adding {} figure(s)", figures.len());
+                    }
+                    let figures_html = generate_figures_html(&crate_info.name, &figures, args.font_size);
+                    match PdfDocument::from_html_with_cache(
+                        &figures_html, &images, &fonts, &pdf_options, &mut Vec::new(),
+                        Some(font_pool.clone()),
+                    ) {
+                        Ok(figures_doc) => combined_doc.append_document(figures_doc),
+                        Err(e) => report_unrendered_figures(&mut report, figures, &e),
+                    }
+                }
+            }
+
+            // Append the commit log for the requested range
+            if let Some((ref from, ref to)) = commit_range {
+                let range = format!("{}..{}", from, to);
+                let log_html = generate_commit_log_html(crate_info, &range, &commits, &crate_prefix, args.font_size);
+                let log_doc = PdfDocument::from_html_with_cache(
+                    &log_html, &BTreeMap::new(), &fonts, &pdf_options, &mut Vec::new(),
+                    Some(font_pool.clone()),
+                ).map_err(|e| anyhow::anyhow!("Failed to generate commit log: {}", e))?;
+                combined_doc.append_document(log_doc);
+            }
+
+            // Report files that are missing or degraded, optionally as an appendix page
+            report.print(&crate_info.name);
+            if args.report_page && !report.is_empty() {
+                let report_html = generate_report_page_html(crate_info, &report, args.font_size);
+                let report_doc = PdfDocument::from_html_with_cache(
+                    &report_html, &BTreeMap::new(), &fonts, &pdf_options, &mut Vec::new(),
+                    Some(font_pool.clone()),
+                ).map_err(|e| anyhow::anyhow!("Failed to generate report page: {}", e))?;
+                combined_doc.append_document(report_doc);
+            }
+
+            // Append the item index last, so readers find it at the end
+            if args.index && !index_entries.is_empty() {
+                index_entries.sort_by_cached_key(|e| (e.item.name.to_lowercase(), e.path.clone(), e.item.line));
+                let index_html = generate_index_html(crate_info, &index_entries, args.font_size);
+                let index_doc = PdfDocument::from_html_with_cache(
+                    &index_html, &BTreeMap::new(), &fonts, &pdf_options, &mut Vec::new(),
+                    Some(font_pool.clone()),
+                ).map_err(|e| anyhow::anyhow!("Failed to generate index: {}", e))?;
+                combined_doc.append_document(index_doc);
+            }
+
+            if args.links {
+                link_symbols(&mut combined_doc, &link_targets(definition_pages));
+            }
+
+            // Save final PDF, or add it to the combined workspace printout
+            let page_count = combined_doc.page_count();
+            let crate_summary = CrateSummary::new(&crate_info.name, &crate_info.version, printed_files);
+            if let Some(ref mut doc) = workspace_doc {
+                doc.append_document(combined_doc);
+                workspace_summaries.push(crate_summary);
+                println!("Added: {} ({} pages)", crate_info.name, page_count);
+            } else {
+                let output_name = match target.label {
+                    Some(ref label) => format!("{}-{}{}.pdf", crate_info.name, sanitize_file_name(label), api_suffix),
+                    None => format!("{}{}.pdf", crate_info.name, api_suffix),
+                };
+                let output_path = args.output.join(output_name);
+                let save_options = PdfSaveOptions::default();
+                let mut save_warnings = Vec::new();
+                let bytes = combined_doc.save(&save_options, &mut save_warnings);
+                fs::write(&output_path, bytes)?;
+                println!("Created: {} ({} pages)", output_path.display(), page_count);
+                if args.json {
+                    write_stats_json(&output_path.with_extension("json"), &[crate_summary])?;
+                }
+            }
+
+            if let Some(ref label) = target.label {
+                comparison.entry(crate_info.name.clone()).or_default().insert(label.clone(), CrateStats {
+                    files: cached_files.len(),
+                    loc: cached_files.iter().map(|f| f.stats.lines).sum(),
+                    pages: page_count,
+                });
+            }
+
+            // Clean up cache directory
+            let _ = fs::remove_dir_all(&cache_dir);
         }
 
-        if args.verbose {
-            println!("  Combined {} files into {} pages", file_count, combined_doc.page_count());
+        if let Some(doc) = workspace_doc {
+            let output_name = match target.label {
+                Some(ref label) => format!("{}-{}{}.pdf", workspace_name, sanitize_file_name(label), api_suffix),
+                None => format!("{}{}.pdf", workspace_name, api_suffix),
+            };
+            let output_path = args.output.join(output_name);
+            let bytes = doc.save(&PdfSaveOptions::default(), &mut Vec::new());
+            fs::write(&output_path, bytes)?;
+            println!("Created: {} ({} pages)", output_path.display(), doc.page_count());
+            if args.json {
+                write_stats_json(&output_path.with_extension("json"), &workspace_summaries)?;
+            }
         }
+    }
 
-        // Save final PDF
-        let output_path = args.output.join(format!("{}.pdf", crate_info.name));
-        let save_options = PdfSaveOptions::default();
-        let mut save_warnings = Vec::new();
-        let bytes = combined_doc.save(&save_options, &mut save_warnings);
+    // Summary page comparing the crates across refs
+    if targets.len() > 1 {
+        let refs: Vec<(String, String)> = targets.iter()
+            .map(|t| (t.label.clone().unwrap_or_default(), t.git_hash.clone().unwrap_or_default()))
+            .collect();
+        let summary_html = generate_ref_comparison_html(&refs, &comparison, args.font_size);
+        let mut fonts: BTreeMap<String, Base64OrRaw> = BTreeMap::new();
+        fonts.insert("RobotoMono".to_string(), Base64OrRaw::Raw((*font_bytes).clone()));
+        let summary_doc = PdfDocument::from_html_with_cache(
+            &summary_html, &BTreeMap::new(), &fonts, &pdf_options, &mut Vec::new(), None,
+        ).map_err(|e| anyhow::anyhow!("Failed to generate comparison summary: {}", e))?;
+        let output_path = args.output.join("ref-comparison.pdf");
+        let bytes = summary_doc.save(&PdfSaveOptions::default(), &mut Vec::new());
         fs::write(&output_path, bytes)?;
-        println!("Created: {} ({} pages)", output_path.display(), combined_doc.page_count());
-
-        // Clean up cache directory
-        let _ = fs::remove_dir_all(&cache_dir);
+        println!("Created: {} ({} pages)", output_path.display(), summary_doc.page_count());
     }
 
     println!("\nDone in {:?}!", start.elapsed());
     Ok(())
 }
 
+/// `--json`: write the statistics of the printed crates, one object per crate
+/// Read `image_files` into an images map for the renderer, with the key and caption of
+/// each figure; images that can't be read are reported
+fn load_figures(image_files: &[ImageFile], report: &mut RenderReport) -> (BTreeMap<String, Base64OrRaw>, Vec<(String, String)>) {
+    let mut images: BTreeMap<String, Base64OrRaw> = BTreeMap::new();
+    let mut figures: Vec<(String, String)> = Vec::new();
+    for (idx, image) in image_files.iter().enumerate() {
+        let caption = image.relative_path.to_string_lossy().to_string();
+        match fs::read(&image.path) {
+            Ok(bytes) => {
+                // Keep the extension so the renderer can tell SVG from raster images
+                let ext = image.path.extension()
+                    .map(|e| e.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                let key = format!("figure{}.{}", idx, ext);
+                images.insert(key.clone(), Base64OrRaw::Raw(bytes));
+                figures.push((key, caption));
+            }
+            Err(e) => report.push(FileIssue::new(caption, IssueKind::RenderFailed, format!("Failed to read image: {}", e))),
+        }
+    }
+    (images, figures)
+}
+
+fn report_unrendered_figures(report: &mut RenderReport, figures: Vec<(String, String)>, error: &impl std::fmt::Display) {
+    for (_, caption) in figures {
+        report.push(FileIssue::new(caption, IssueKind::RenderFailed, format!("Failed to render figure: {}", error)));
+    }
+}
+
+fn write_stats_json(path: &Path, crates: &[CrateSummary]) -> Result<()> {
+    let json = serde_json::to_string_pretty(crates)?;
+    fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+    println!("Created: {}", path.display());
+    Ok(())
+}
+
+/// `--list-refs`: print branches and tags to pick a `--ref` from
+fn print_refs(repo_path: &Path) -> Result<()> {
+    let listing = list_refs(repo_path)?;
+    if let Some(ref default_branch) = listing.default_branch {
+        println!("Default branch: {}", default_branch);
+    }
+    println!("Branches:");
+    for (name, hash) in &listing.branches {
+        let marker = if listing.default_branch.as_deref() == Some(name.as_str()) { "*" } else { " " };
+        println!("  {} {:<40} {}", marker, name, hash);
+    }
+    println!("Tags:");
+    for (name, hash) in &listing.tags {
+        println!("    {:<40} {}", name, hash);
+    }
+    Ok(())
+}
+
+/// Directory for clones, exports and intermediate files
+fn temp_dir(args: &Args) -> PathBuf {
+    args.temp_dir.clone().unwrap_or_else(|| {
+        std::env::temp_dir().join("git2pdf")
+    })
+}
+
+/// `git2pdf cache list` / `git2pdf cache prune`
+fn run_cache_command(action: &CacheAction, temp_dir: &Path) -> Result<()> {
+    let cache = CloneCache::new(temp_dir);
+    match *action {
+        CacheAction::List => {
+            let entries = cache.entries()?;
+            if entries.is_empty() {
+                println!("No cached clones in {}", temp_dir.display());
+            }
+            for entry in entries {
+                let age = entry.last_used
+                    .and_then(|t| t.elapsed().ok())
+                    .map(|d| format_age((d.as_secs() / 86400) as i64))
+                    .unwrap_or_else(|| "incomplete".to_string());
+                println!("{:<40} {:>10} {:>10}  {}",
+                    entry.key, format_size(entry.size), age, entry.url.as_deref().unwrap_or("-"));
+            }
+        }
+        CacheAction::Prune { older_than, all } => {
+            let max_age = (!all).then(|| std::time::Duration::from_secs(older_than * 86400));
+            let removed = cache.prune(max_age)?;
+            let freed: u64 = removed.iter().map(|e| e.size).sum();
+            for entry in &removed {
+                println!("Removed {}", entry.key);
+            }
+            println!("Removed {} clone(s), freed {}", removed.len(), format_size(freed));
+        }
+    }
+    Ok(())
+}
+
 /// Process a single file directly — bypasses git/crate discovery.
 /// Useful for benchmarking layout performance on files of varying size.
 fn process_single_file(
@@ -517,8 +1576,19 @@ fn process_single_file(
         .unwrap_or_else(|| "file".to_string());
 
     // Read file content and count LOC
-    let content = fs::read_to_string(file_path)
-        .with_context(|| format!("Failed to read: {}", file_path.display()))?;
+    let content = match load_file(file_path)
+        .with_context(|| format!("Failed to read: {}", file_path.display()))?
+    {
+        FileContent::Text { content, decoded_from } => {
+            if let Some(encoding) = decoded_from {
+                eprintln!("[warning] {} is not valid UTF-8, decoded as {}", file_name, encoding);
+            }
+            content
+        }
+        FileContent::Binary { bytes } => {
+            bail!("{} is a binary file ({})", file_path.display(), format_size(bytes.len() as u64))
+        }
+    };
     let loc = content.lines().count();
     let content_bytes = content.len();
     eprintln!("[timing] file={}, LOC={}, bytes={}", file_name, loc, content_bytes);
@@ -541,11 +1611,16 @@ fn process_single_file(
         relative_path: PathBuf::from(&file_name),
         category: FileCategory::Source,
         module_path: String::new(),
+        section: None,
     };
 
     // Generate HTML
     let t1 = Instant::now();
-    let html = generate_html_for_single_file(&source_file, &syntax_set, theme, args.font_size)?;
+    let mut highlight_warnings = Vec::new();
+    let html = generate_html_for_single_file(&source_file, &content, &syntax_set, theme, args.font_size, &[], &mut highlight_warnings)?;
+    for w in &highlight_warnings {
+        eprintln!("[warning] highlighting failed at {}", w);
+    }
     let html_elapsed = t1.elapsed();
     eprintln!("[timing] html_generation: {:.1?} ({} bytes HTML)", html_elapsed, html.len());
 
@@ -620,8 +1695,9 @@ fn extract_repo_name(url: &str) -> Result<String> {
     // https://github.com/user/repo.git
     // git@github.com:user/repo.git
     // ssh://git@github.com/user/repo.git
+    // file:///srv/mirrors/repo.git/
     
-    let url = url.trim_end_matches(".git");
+    let url = url.trim_end_matches('/').trim_end_matches(".git");
     
     if let Some(name) = url.rsplit('/').next() {
         if !name.is_empty() {
@@ -641,41 +1717,6 @@ fn extract_repo_name(url: &str) -> Result<String> {
     bail!("Could not extract repository name from URL: {}", url)
 }
 
-/// Run cargo fmt on a repository with specified line width
-fn run_cargo_fmt(repo_path: &Path, line_width: u32, verbose: bool) -> Result<()> {
-    // Create a rustfmt.toml with the specified line width
-    let rustfmt_config = format!("max_width = {}\n", line_width);
-    let rustfmt_path = repo_path.join("rustfmt.toml");
-    
-    // Only write if it doesn't exist (don't override existing config)
-    if !rustfmt_path.exists() {
-        fs::write(&rustfmt_path, &rustfmt_config)?;
-    }
-    
-    let output = Command::new("cargo")
-        .arg("fmt")
-        .arg("--manifest-path")
-        .arg(repo_path.join("Cargo.toml"))
-        .output()
-        .context("Failed to run cargo fmt. Is cargo installed?")?;
-    
-    if verbose {
-        if !output.stdout.is_empty() {
-            println!("  cargo fmt stdout: {}", String::from_utf8_lossy(&output.stdout));
-        }
-        if !output.stderr.is_empty() {
-            println!("  cargo fmt stderr: {}", String::from_utf8_lossy(&output.stderr));
-        }
-    }
-    
-    // Don't fail if cargo fmt fails (repo might not be a valid Rust project)
-    if !output.status.success() && verbose {
-        println!("  Warning: cargo fmt exited with non-zero status");
-    }
-    
-    Ok(())
-}
-
 /// Copy repository files to destination, respecting .gitignore
 fn copy_repo_files(src: &Path, dst: &Path, verbose: bool) -> Result<()> {
     // Remove destination if it exists
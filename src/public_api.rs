@@ -0,0 +1,456 @@
+//! Public API extraction for `--public-api`
+//!
+//! Each Rust file is parsed with syn and reduced to its public surface: `pub` items
+//! with their doc comments and signatures. Function bodies, private items and private
+//! fields are omitted, trait implementations are listed without their items. Every file
+//! is reduced on its own, so items re-exported from private modules appear in the file
+//! that defines them only if they are declared `pub` there.
+//!
+//! The output is valid Rust syntax (functions end in `;` instead of a body) but its
+//! token spacing is not, so it is meant to be passed through rustfmt.
+
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use quote::{quote, ToTokens};
+use syn::{
+    AttrStyle, Attribute, Expr, Fields, ImplItem, Item, Lit, Meta, TraitItem, Type, Visibility,
+};
+
+/// Attributes that are part of an item's API and kept next to its signature
+const KEPT_ATTRIBUTES: &[&str] = &["derive", "cfg", "repr", "non_exhaustive", "deprecated", "must_use"];
+
+/// Reduce a Rust source file to its public items. Returns an empty string if the file
+/// has none.
+pub fn public_api(content: &str) -> Result<String> {
+    let file = syn::parse_file(content).context("Failed to parse file")?;
+    let mut api = Api {
+        out: String::new(),
+        indent: 0,
+        private_types: private_types(&file.items),
+    };
+    api.items(&file.items);
+    if api.out.is_empty() {
+        return Ok(String::new());
+    }
+
+    // Module docs (`//!`) only make sense above public items
+    let mut out = String::new();
+    for line in doc_lines(&file.attrs, true) {
+        out.push_str(&format!("//!{}\n", line));
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out.push_str(&api.out);
+    Ok(out)
+}
+
+struct Api {
+    out: String,
+    indent: usize,
+    /// Types declared without `pub` in this file; their impls are left out
+    private_types: HashSet<String>,
+}
+
+impl Api {
+    fn line(&mut self, line: &str) {
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    /// Doc comments as `///` lines, then the kept attributes
+    fn attrs(&mut self, attrs: &[Attribute]) {
+        for line in doc_lines(attrs, false) {
+            self.line(&format!("///{}", line));
+        }
+        for attr in attrs.iter().filter(|a| is_kept(a)) {
+            self.line(&attr.to_token_stream().to_string());
+        }
+    }
+
+    /// A public item, separated from the previous one by a blank line
+    fn item_start(&mut self, attrs: &[Attribute]) {
+        if !self.out.is_empty() && !self.out.ends_with("{\n") {
+            self.line("");
+        }
+        self.attrs(attrs);
+    }
+
+    fn items(&mut self, items: &[Item]) {
+        for item in items {
+            self.item(item);
+        }
+    }
+
+    fn item(&mut self, item: &Item) {
+        match item {
+            Item::Fn(f) if is_public(&f.vis, &f.attrs) => {
+                let (vis, sig) = (&f.vis, &f.sig);
+                self.item_start(&f.attrs);
+                self.line(&quote!(#vis #sig;).to_string());
+            }
+            Item::Struct(s) if is_public(&s.vis, &s.attrs) => {
+                let (vis, ident, generics) = (&s.vis, &s.ident, &s.generics);
+                let where_clause = &generics.where_clause;
+                self.item_start(&s.attrs);
+                let header = quote!(#vis struct #ident #generics).to_string();
+                self.fields(&header, &s.fields, where_clause);
+            }
+            Item::Union(u) if is_public(&u.vis, &u.attrs) => {
+                let (vis, ident, generics) = (&u.vis, &u.ident, &u.generics);
+                let where_clause = &generics.where_clause;
+                self.item_start(&u.attrs);
+                let header = quote!(#vis union #ident #generics).to_string();
+                self.fields(&header, &Fields::Named(u.fields.clone()), where_clause);
+            }
+            Item::Enum(e) if is_public(&e.vis, &e.attrs) => {
+                let (vis, ident, generics) = (&e.vis, &e.ident, &e.generics);
+                let where_clause = &generics.where_clause;
+                self.item_start(&e.attrs);
+                self.line(&format!("{} {{", quote!(#vis enum #ident #generics #where_clause)));
+                self.indent += 1;
+                for variant in &e.variants {
+                    self.attrs(&variant.attrs);
+                    let mut fields = variant.fields.clone();
+                    fields.iter_mut().for_each(|f| f.attrs.retain(is_kept));
+                    let discriminant = variant.discriminant.as_ref().map(|(eq, expr)| quote!(#eq #expr));
+                    let ident = &variant.ident;
+                    self.line(&format!("{},", quote!(#ident #fields #discriminant)));
+                }
+                self.indent -= 1;
+                self.line("}");
+            }
+            Item::Trait(t) if is_public(&t.vis, &t.attrs) => {
+                let (vis, unsafety, auto, ident, generics) = (&t.vis, &t.unsafety, &t.auto_token, &t.ident, &t.generics);
+                let (colon, supertraits, where_clause) = (&t.colon_token, &t.supertraits, &generics.where_clause);
+                self.item_start(&t.attrs);
+                self.line(&format!(
+                    "{} {{",
+                    quote!(#vis #unsafety #auto trait #ident #generics #colon #supertraits #where_clause)
+                ));
+                self.indent += 1;
+                for trait_item in &t.items {
+                    // Default bodies and values are implementation details
+                    let (attrs, tokens) = match trait_item {
+                        TraitItem::Fn(f) => {
+                            let sig = &f.sig;
+                            (&f.attrs, quote!(#sig;))
+                        }
+                        TraitItem::Const(c) => {
+                            let (ident, generics, ty) = (&c.ident, &c.generics, &c.ty);
+                            (&c.attrs, quote!(const #ident #generics: #ty;))
+                        }
+                        TraitItem::Type(ty) => {
+                            let (ident, generics, colon, bounds) = (&ty.ident, &ty.generics, &ty.colon_token, &ty.bounds);
+                            (&ty.attrs, quote!(type #ident #generics #colon #bounds;))
+                        }
+                        _ => continue,
+                    };
+                    self.attrs(attrs);
+                    self.line(&tokens.to_string());
+                }
+                self.indent -= 1;
+                self.line("}");
+            }
+            Item::Impl(i) if !self.is_private_type(&i.self_ty) && !is_hidden(&i.attrs) => {
+                let (unsafety, generics, self_ty) = (&i.unsafety, &i.generics, &i.self_ty);
+                let where_clause = &generics.where_clause;
+                match &i.trait_ {
+                    Some((bang, path, for_token)) => {
+                        self.item_start(&i.attrs);
+                        self.line(&format!(
+                            "{} {{}}",
+                            quote!(#unsafety impl #generics #bang #path #for_token #self_ty #where_clause)
+                        ));
+                    }
+                    None => {
+                        let public: Vec<&ImplItem> = i.items.iter()
+                            .filter(|item| match item {
+                                ImplItem::Fn(f) => is_public(&f.vis, &f.attrs),
+                                ImplItem::Const(c) => is_public(&c.vis, &c.attrs),
+                                _ => false,
+                            })
+                            .collect();
+                        if public.is_empty() {
+                            return;
+                        }
+                        self.item_start(&i.attrs);
+                        self.line(&format!("{} {{", quote!(#unsafety impl #generics #self_ty #where_clause)));
+                        self.indent += 1;
+                        for (n, impl_item) in public.into_iter().enumerate() {
+                            if n > 0 {
+                                self.line("");
+                            }
+                            match impl_item {
+                                ImplItem::Fn(f) => {
+                                    let (vis, sig) = (&f.vis, &f.sig);
+                                    self.attrs(&f.attrs);
+                                    self.line(&quote!(#vis #sig;).to_string());
+                                }
+                                ImplItem::Const(c) => {
+                                    let (vis, ident, ty, expr) = (&c.vis, &c.ident, &c.ty, &c.expr);
+                                    self.attrs(&c.attrs);
+                                    self.line(&quote!(#vis const #ident: #ty = #expr;).to_string());
+                                }
+                                _ => {}
+                            }
+                        }
+                        self.indent -= 1;
+                        self.line("}");
+                    }
+                }
+            }
+            Item::Mod(m) if is_public(&m.vis, &m.attrs) => {
+                let (vis, unsafety, ident) = (&m.vis, &m.unsafety, &m.ident);
+                self.item_start(&m.attrs);
+                match &m.content {
+                    // The module's own file is printed separately
+                    None => self.line(&quote!(#vis #unsafety mod #ident;).to_string()),
+                    Some((_, items)) => {
+                        self.line(&format!("{} {{", quote!(#vis #unsafety mod #ident)));
+                        self.indent += 1;
+                        self.items(items);
+                        self.indent -= 1;
+                        self.line("}");
+                    }
+                }
+            }
+            Item::ForeignMod(f) => {
+                let public: Vec<syn::ForeignItem> = f.items.iter()
+                    .filter(|item| match item {
+                        syn::ForeignItem::Fn(f) => is_public(&f.vis, &f.attrs),
+                        syn::ForeignItem::Static(s) => is_public(&s.vis, &s.attrs),
+                        _ => false,
+                    })
+                    .cloned()
+                    .collect();
+                if public.is_empty() {
+                    return;
+                }
+                let (unsafety, abi) = (&f.unsafety, &f.abi);
+                self.item_start(&f.attrs);
+                self.line(&format!("{} {{", quote!(#unsafety #abi)));
+                self.indent += 1;
+                for mut foreign_item in public {
+                    let attrs = match &mut foreign_item {
+                        syn::ForeignItem::Fn(f) => std::mem::take(&mut f.attrs),
+                        syn::ForeignItem::Static(s) => std::mem::take(&mut s.attrs),
+                        _ => Vec::new(),
+                    };
+                    self.attrs(&attrs);
+                    self.line(&foreign_item.to_token_stream().to_string());
+                }
+                self.indent -= 1;
+                self.line("}");
+            }
+            Item::Const(c) if is_public(&c.vis, &c.attrs) => self.plain(item),
+            Item::Static(s) if is_public(&s.vis, &s.attrs) => self.plain(item),
+            Item::Type(t) if is_public(&t.vis, &t.attrs) => self.plain(item),
+            Item::TraitAlias(t) if is_public(&t.vis, &t.attrs) => self.plain(item),
+            Item::Use(u) if is_public(&u.vis, &u.attrs) => self.plain(item),
+            Item::ExternCrate(e) if is_public(&e.vis, &e.attrs) => self.plain(item),
+            Item::Macro(m) if m.ident.is_some() && m.attrs.iter().any(|a| a.path().is_ident("macro_export")) => {
+                let mut mac = m.clone();
+                mac.attrs.clear();
+                self.item_start(&m.attrs);
+                self.line(&mac.to_token_stream().to_string());
+            }
+            _ => {}
+        }
+    }
+
+    /// A struct or union with its public fields, noting omitted private ones
+    fn fields(&mut self, header: &str, fields: &Fields, where_clause: &Option<syn::WhereClause>) {
+        let where_clause = where_clause.as_ref()
+            .map(|w| format!(" {}", w.to_token_stream()))
+            .unwrap_or_default();
+        let private = fields.iter().filter(|f| !is_public(&f.vis, &f.attrs)).count();
+        match fields {
+            Fields::Named(named) => {
+                self.line(&format!("{}{} {{", header, where_clause));
+                self.indent += 1;
+                for field in named.named.iter().filter(|f| is_public(&f.vis, &f.attrs)) {
+                    let (vis, ident, ty) = (&field.vis, &field.ident, &field.ty);
+                    self.attrs(&field.attrs);
+                    self.line(&format!("{},", quote!(#vis #ident: #ty)));
+                }
+                if private > 0 {
+                    self.line(&format!("// {} private field(s) omitted", private));
+                }
+                self.indent -= 1;
+                self.line("}");
+            }
+            // Private tuple fields are shown as `_`, like rustdoc does
+            Fields::Unnamed(unnamed) => {
+                let fields: Vec<String> = unnamed.unnamed.iter()
+                    .map(|f| if is_public(&f.vis, &f.attrs) {
+                        let (vis, ty) = (&f.vis, &f.ty);
+                        quote!(#vis #ty).to_string()
+                    } else {
+                        "_".to_string()
+                    })
+                    .collect();
+                self.line(&format!("{}({}){};", header, fields.join(", "), where_clause));
+            }
+            Fields::Unit => self.line(&format!("{}{};", header, where_clause)),
+        }
+    }
+
+    /// An item printed as it is, with its doc comments as `///` lines
+    fn plain(&mut self, item: &Item) {
+        let mut item = item.clone();
+        let attrs = item_attrs(&mut item).map(std::mem::take).unwrap_or_default();
+        self.item_start(&attrs);
+        self.line(&item.to_token_stream().to_string());
+    }
+
+    /// Whether `ty` names a type declared without `pub` in this file
+    fn is_private_type(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Path(path) => path.path.segments.last()
+                .is_some_and(|s| self.private_types.contains(&s.ident.to_string())),
+            _ => false,
+        }
+    }
+}
+
+/// Whether an item is part of the public API: `pub` (not `pub(crate)` and the like),
+/// not `#[doc(hidden)]` and not test-only
+fn is_public(vis: &Visibility, attrs: &[Attribute]) -> bool {
+    matches!(vis, Visibility::Public(_)) && !is_hidden(attrs)
+}
+
+fn is_hidden(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| match &attr.meta {
+        Meta::List(list) if list.path.is_ident("doc") => list.tokens.to_string() == "hidden",
+        Meta::List(list) if list.path.is_ident("cfg") => list.tokens.to_string() == "test",
+        _ => false,
+    })
+}
+
+fn is_kept(attr: &Attribute) -> bool {
+    matches!(attr.style, AttrStyle::Outer) && KEPT_ATTRIBUTES.iter().any(|name| attr.path().is_ident(name))
+}
+
+/// Lines of the `#[doc = "..."]` attributes: `//!` comments if `inner`, otherwise `///`
+fn doc_lines(attrs: &[Attribute], inner: bool) -> Vec<String> {
+    attrs.iter()
+        .filter(|attr| matches!(attr.style, AttrStyle::Inner(_)) == inner && attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(nv) => match &nv.value {
+                Expr::Lit(lit) => match &lit.lit {
+                    Lit::Str(s) => Some(s.value()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .flat_map(|doc| doc.split('\n').map(|line| line.trim_end_matches('\r').to_string()).collect::<Vec<_>>())
+        .collect()
+}
+
+/// Attributes of the items printed as they are
+fn item_attrs(item: &mut Item) -> Option<&mut Vec<Attribute>> {
+    Some(match item {
+        Item::Const(i) => &mut i.attrs,
+        Item::Static(i) => &mut i.attrs,
+        Item::Type(i) => &mut i.attrs,
+        Item::TraitAlias(i) => &mut i.attrs,
+        Item::Use(i) => &mut i.attrs,
+        Item::ExternCrate(i) => &mut i.attrs,
+        _ => return None,
+    })
+}
+
+/// Names of the structs, enums, unions and type aliases declared without `pub`
+fn private_types(items: &[Item]) -> HashSet<String> {
+    items.iter()
+        .filter_map(|item| match item {
+            Item::Struct(s) if !matches!(s.vis, Visibility::Public(_)) => Some(s.ident.to_string()),
+            Item::Enum(e) if !matches!(e.vis, Visibility::Public(_)) => Some(e.ident.to_string()),
+            Item::Union(u) if !matches!(u.vis, Visibility::Public(_)) => Some(u.ident.to_string()),
+            Item::Type(t) if !matches!(t.vis, Visibility::Public(_)) => Some(t.ident.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_api() {
+        let source = r#"
+//! Shapes
+
+use std::fmt;
+
+/// A point
+#[derive(Debug, Clone)]
+pub struct Point {
+    /// Horizontal
+    pub x: f32,
+    pub y: f32,
+    cache: u32,
+}
+
+pub struct Id(pub u32, u64);
+
+struct Private;
+
+impl Point {
+    /// Distance to the origin
+    pub fn length(&self) -> f32 {
+        let secret_body = self.x * self.x + self.y * self.y;
+        secret_body.sqrt()
+    }
+
+    fn helper(&self) {}
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+impl Private {
+    pub fn unreachable(&self) {}
+}
+
+pub trait Shape {
+    fn area(&self) -> f32 { 0.0 }
+}
+
+pub(crate) fn internal() {}
+
+#[doc(hidden)]
+pub fn hidden() {}
+
+#[cfg(test)]
+pub mod tests {}
+"#;
+        let api = public_api(source).unwrap();
+        assert!(api.starts_with("//! Shapes\n"));
+        assert!(api.contains("/// A point\n# [derive (Debug , Clone)]\npub struct Point"));
+        assert!(api.contains("/// Horizontal\n    pub x : f32,"));
+        assert!(api.contains("// 1 private field(s) omitted"));
+        assert!(api.contains("pub struct Id(pub u32, _);"));
+        assert!(api.contains("/// Distance to the origin\n    pub fn length (& self) -> f32 ;"));
+        assert!(api.contains("impl fmt :: Display for Point {}"));
+        assert!(api.contains("fn area (& self) -> f32 ;"));
+        for omitted in ["secret_body", "helper", "cache", "Private", "internal", "hidden", "mod tests", "use std"] {
+            assert!(!api.contains(omitted), "{} should be omitted:\n{}", omitted, api);
+        }
+        // The output is valid Rust syntax
+        syn::parse_file(&api).unwrap();
+
+        assert_eq!(public_api("fn main() {}\n").unwrap(), "");
+    }
+}
@@ -0,0 +1,136 @@
+//! Per-file problem reporting
+//!
+//! Collects files that could not be formatted, highlighted or rendered, so the
+//! user knows when a printout is incomplete.
+
+use std::fmt;
+
+/// Kind of problem encountered while processing a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IssueKind {
+    /// rustfmt failed, the file was printed unformatted
+    FormatFailed,
+    /// Syntax highlighting failed, (parts of) the file were printed unhighlighted
+    HighlightFailed,
+    /// The file could not be parsed for `--public-api` and was printed in full
+    ParseFailed,
+    /// The file is not valid UTF-8 and was decoded lossily
+    NonUtf8,
+    /// The file is binary and was printed as a placeholder or hex dump
+    Binary,
+    /// Git blame failed, the file was printed without blame annotations
+    BlameFailed,
+    /// A symlink in a git export, which only contains regular files
+    Symlink,
+    /// The per-file PDF could not be generated, the file is missing from the output
+    RenderFailed,
+}
+
+impl IssueKind {
+    /// Short human-readable label
+    pub fn label(&self) -> &'static str {
+        match self {
+            IssueKind::FormatFailed => "not formatted",
+            IssueKind::HighlightFailed => "not highlighted",
+            IssueKind::ParseFailed => "not parsed (printed in full)",
+            IssueKind::NonUtf8 => "decoded (non-UTF-8)",
+            IssueKind::Binary => "binary",
+            IssueKind::BlameFailed => "no blame",
+            IssueKind::Symlink => "missing (symlink)",
+            IssueKind::RenderFailed => "missing (render failed)",
+        }
+    }
+
+    /// Whether the file is missing from the printout entirely
+    pub fn is_missing(&self) -> bool {
+        matches!(self, IssueKind::Symlink | IssueKind::RenderFailed)
+    }
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// A problem with a single file
+#[derive(Debug, Clone)]
+pub struct FileIssue {
+    /// Path relative to the crate root
+    pub path: String,
+    /// What went wrong
+    pub kind: IssueKind,
+    /// Error message
+    pub message: String,
+}
+
+impl FileIssue {
+    pub fn new(path: impl Into<String>, kind: IssueKind, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+/// All problems encountered while processing one crate
+#[derive(Debug, Clone, Default)]
+pub struct RenderReport {
+    pub issues: Vec<FileIssue>,
+}
+
+impl RenderReport {
+    pub fn push(&mut self, issue: FileIssue) {
+        self.issues.push(issue);
+    }
+
+    pub fn extend(&mut self, issues: impl IntoIterator<Item = FileIssue>) {
+        self.issues.extend(issues);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Number of files missing from the printout
+    pub fn missing_count(&self) -> usize {
+        self.issues.iter().filter(|i| i.kind.is_missing()).count()
+    }
+
+    /// Issues sorted by kind, then path
+    pub fn sorted(&self) -> Vec<&FileIssue> {
+        let mut issues: Vec<&FileIssue> = self.issues.iter().collect();
+        issues.sort_by(|a, b| (a.kind, &a.path).cmp(&(b.kind, &b.path)));
+        issues
+    }
+
+    /// Print the report to stderr
+    pub fn print(&self, crate_name: &str) {
+        if self.is_empty() {
+            return;
+        }
+        eprintln!("  {} problem(s) in {} ({} file(s) missing from the PDF):",
+            self.issues.len(), crate_name, self.missing_count());
+        for issue in self.sorted() {
+            eprintln!("    {:<24} {}: {}", issue.kind.label(), issue.path, issue.message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_sorting_and_counts() {
+        let mut report = RenderReport::default();
+        report.push(FileIssue::new("src/b.rs", IssueKind::RenderFailed, "layout error"));
+        report.push(FileIssue::new("src/a.rs", IssueKind::FormatFailed, "rustfmt failed"));
+        report.push(FileIssue::new("src/c.rs", IssueKind::NonUtf8, "decoded as windows-1252"));
+
+        assert_eq!(report.missing_count(), 1);
+        let sorted: Vec<&str> = report.sorted().iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(sorted, vec!["src/a.rs", "src/c.rs", "src/b.rs"]);
+    }
+}
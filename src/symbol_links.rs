@@ -0,0 +1,289 @@
+//! Symbol links for `--links`
+//!
+//! Identifiers in the printed code link to the page of their definition in the same PDF.
+//! Names are resolved lexically against the items defined in the crate (see
+//! [`crate::item_index`]): calls of free functions and macros, capitalized names (types
+//! and traits), `Type::name` paths and `.method()` calls. Names defined more than once
+//! are left unlinked rather than guessed.
+//!
+//! The per-file HTML carries `<a href="#sym-NAME">` links; the pages of the definitions
+//! are only known after all files have been rendered, so the links are turned into page
+//! destinations on the finished document by [`link_symbols`].
+
+use std::collections::HashMap;
+
+use printpdf::{Actions, Destination, Op, PdfDocument};
+use proc_macro2::{Delimiter, Ident, TokenStream, TokenTree};
+
+use crate::item_index::{IndexedItem, ItemKind};
+
+/// `href` prefix of symbol links in the generated HTML
+pub const SYMBOL_LINK_PREFIX: &str = "#sym-";
+
+/// Keywords after which an identifier is being defined, not referenced
+const DEFINITION_KEYWORDS: &[&str] = &["fn", "struct", "enum", "union", "trait", "type", "mod"];
+
+/// A link from an identifier to the definition it refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolLink {
+    /// Byte range of the identifier in its line
+    pub start: usize,
+    pub end: usize,
+    /// Indexed name of the definition (`parse_margins`, `Point::new`)
+    pub target: String,
+}
+
+/// Items of a crate by the names they can be referenced with. `None` marks names that
+/// don't resolve to exactly one definition.
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    /// Functions, types, traits and macros by their name without module prefix
+    free: HashMap<String, Option<String>>,
+    /// Methods by `Type::method`
+    qualified: HashMap<String, Option<String>>,
+    /// Methods by their name alone, for `.method()` calls
+    methods: HashMap<String, Option<String>>,
+}
+
+impl SymbolTable {
+    pub fn new<'a>(items: impl IntoIterator<Item = &'a IndexedItem>) -> Self {
+        let items: Vec<&IndexedItem> = items.into_iter()
+            .filter(|item| !matches!(item.kind, ItemKind::Impl { .. }))
+            .collect();
+        let definitions = definition_counts(items.iter().copied());
+        let mut table = Self::default();
+        for item in items {
+            // The same name defined twice (`parse` in two files, `fmt` in the Debug and
+            // Display impls of a type) can't be told apart
+            let target = (definitions[item.name.as_str()] == 1).then_some(item.name.as_str());
+            let short = item.name.rsplit("::").next().unwrap_or(&item.name);
+            if item.kind == ItemKind::Method {
+                insert(&mut table.qualified, &item.name, target);
+                insert(&mut table.methods, short, target);
+            } else {
+                insert(&mut table.free, short, target);
+            }
+        }
+        table
+    }
+
+    /// Links in `content`, per line (0-based). Files that don't tokenize get no links.
+    pub fn links(&self, content: &str) -> Vec<Vec<SymbolLink>> {
+        let lines: Vec<&str> = content.lines().collect();
+        let mut links = vec![Vec::new(); lines.len()];
+        if let Ok(stream) = content.parse::<TokenStream>() {
+            self.walk(stream, &lines, &mut links);
+        }
+        for line in &mut links {
+            line.sort_by_key(|link| link.start);
+        }
+        links
+    }
+
+    fn walk(&self, stream: TokenStream, lines: &[&str], links: &mut [Vec<SymbolLink>]) {
+        let tokens: Vec<TokenTree> = stream.into_iter().collect();
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                TokenTree::Group(group) => self.walk(group.stream(), lines, links),
+                TokenTree::Ident(ident) => {
+                    let Some(target) = self.resolve(&tokens, i, ident) else {
+                        continue;
+                    };
+                    let start = ident.span().start();
+                    let Some(line) = lines.get(start.line.wrapping_sub(1)) else {
+                        continue;
+                    };
+                    // Span columns count characters, links use byte offsets
+                    let byte = |column: usize| line.char_indices().nth(column).map_or(line.len(), |(b, _)| b);
+                    let name_len = ident.to_string().chars().count();
+                    links[start.line - 1].push(SymbolLink {
+                        start: byte(start.column),
+                        end: byte(start.column + name_len),
+                        target,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The definition the identifier `tokens[i]` refers to, if it is unambiguous
+    fn resolve(&self, tokens: &[TokenTree], i: usize, ident: &Ident) -> Option<String> {
+        let name = ident.to_string();
+        let prev = |n: usize| i.checked_sub(n).and_then(|j| tokens.get(j));
+        let next = tokens.get(i + 1);
+
+        match prev(1) {
+            Some(TokenTree::Ident(keyword)) if DEFINITION_KEYWORDS.contains(&keyword.to_string().as_str()) => {
+                return None;
+            }
+            // `macro_rules! name`
+            Some(TokenTree::Punct(p)) if p.as_char() == '!'
+                && matches!(prev(2), Some(TokenTree::Ident(m)) if m == "macro_rules") => return None,
+            _ => {}
+        }
+
+        if is_punct(prev(1), ':') && is_punct(prev(2), ':') {
+            return match prev(3) {
+                // `Type::name`: only methods of crate types, not `String::new`
+                Some(TokenTree::Ident(ty)) if ty.to_string().starts_with(char::is_uppercase) => {
+                    lookup(&self.qualified, &format!("{}::{}", ty, name))
+                }
+                // `crate::module::name`
+                _ => lookup(&self.free, &name),
+            };
+        }
+
+        if is_punct(prev(1), '.') {
+            let is_call = matches!(next, Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis)
+                || is_punct(next, ':');
+            return if is_call { lookup(&self.methods, &name) } else { None };
+        }
+
+        // Plain names: calls, macro invocations and capitalized type or trait names;
+        // other lowercase names are most likely variables
+        let is_call = matches!(next, Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis);
+        if is_call || is_punct(next, '!') || name.starts_with(char::is_uppercase) {
+            lookup(&self.free, &name)
+        } else {
+            None
+        }
+    }
+}
+
+/// Number of definitions of each indexed name
+pub fn definition_counts<'a>(items: impl IntoIterator<Item = &'a IndexedItem>) -> HashMap<&'a str, usize> {
+    let mut counts = HashMap::new();
+    for item in items {
+        *counts.entry(item.name.as_str()).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Pages of the definitions links can point to (indexed name -> page), from the items of
+/// a crate with the pages they were printed on. Impl blocks are not definitions: a link to
+/// a type lands on the type, not on its last impl. Names defined more than once and items
+/// without a known page are left out.
+pub fn link_targets<'a>(items: impl IntoIterator<Item = (&'a IndexedItem, Option<usize>)>) -> HashMap<String, usize> {
+    let items: Vec<(&IndexedItem, Option<usize>)> = items.into_iter()
+        .filter(|(item, _)| !matches!(item.kind, ItemKind::Impl { .. }))
+        .collect();
+    let definitions = definition_counts(items.iter().map(|(item, _)| *item));
+    items.iter()
+        .filter(|(item, _)| definitions[item.name.as_str()] == 1)
+        .filter_map(|(item, page)| Some((item.name.clone(), (*page)?)))
+        .collect()
+}
+
+fn insert(map: &mut HashMap<String, Option<String>>, key: &str, target: Option<&str>) {
+    map.entry(key.to_string())
+        .and_modify(|existing| if existing.as_deref() != target {
+            *existing = None;
+        })
+        .or_insert_with(|| target.map(str::to_string));
+}
+
+fn lookup(map: &HashMap<String, Option<String>>, key: &str) -> Option<String> {
+    map.get(key).cloned().flatten()
+}
+
+fn is_punct(token: Option<&TokenTree>, c: char) -> bool {
+    matches!(token, Some(TokenTree::Punct(p)) if p.as_char() == c)
+}
+
+/// Turn the symbol links in `doc` into links to the pages of their definitions
+/// (`targets`: indexed name -> 1-based page). Links to definitions that are not in the
+/// printout (e.g. private items with `--public-api`) or whose page is unknown are removed.
+pub fn link_symbols(doc: &mut PdfDocument, targets: &HashMap<String, usize>) {
+    for page in &mut doc.pages {
+        page.ops.retain_mut(|op| {
+            let Op::LinkAnnotation { link } = op else {
+                return true;
+            };
+            let target = match &link.actions {
+                Actions::Uri(uri) => uri.strip_prefix(SYMBOL_LINK_PREFIX).map(|name| targets.get(name).copied()),
+                _ => None,
+            };
+            match target {
+                None => true,
+                Some(None) => false,
+                Some(Some(page)) => {
+                    link.actions = Actions::Goto(Destination::Xyz { page, left: None, top: None, zoom: None });
+                    true
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item_index::index_items;
+
+    #[test]
+    fn test_links() {
+        let source = "\
+struct Point;
+
+impl Point {
+    fn new() -> Point {
+        Point
+    }
+    fn length(&self) -> f32 {
+        0.0
+    }
+}
+
+fn parse_margins() {}
+
+fn main() {
+    let p = Point::new();
+    let length = p.length();
+    parse_margins();
+    let s = String::new();
+    let q = \"parse_margins()\";
+}
+";
+        let table = SymbolTable::new(&index_items(source).unwrap());
+        let per_line = table.links(source);
+        let links: Vec<(usize, &str, &str)> = per_line.iter()
+            .enumerate()
+            .flat_map(|(line, links)| {
+                links.iter().map(move |l| (line + 1, &source.lines().nth(line).unwrap()[l.start..l.end], l.target.as_str()))
+            })
+            .collect();
+        assert_eq!(links, [
+            (3, "Point", "Point"),
+            (4, "Point", "Point"),
+            (5, "Point", "Point"),
+            (15, "Point", "Point"),
+            (15, "new", "Point::new"),
+            (16, "length", "Point::length"),
+            (17, "parse_margins", "parse_margins"),
+        ]);
+
+        // A second file defining `parse_margins` and another `Point::length` makes both ambiguous
+        let other = "impl Point {\n    fn length(&self) -> f32 { 1.0 }\n}\nfn parse_margins() {}\n";
+        let items: Vec<IndexedItem> = index_items(source).unwrap().into_iter()
+            .chain(index_items(other).unwrap())
+            .collect();
+        let table = SymbolTable::new(&items);
+        let targets: Vec<String> = table.links(source).into_iter().flatten().map(|l| l.target).collect();
+        assert_eq!(targets, ["Point", "Point", "Point", "Point", "Point::new"]);
+    }
+
+    #[test]
+    fn test_link_targets() {
+        // A struct on page 3 and its impl in a later file on page 7
+        let types = index_items("struct Point;\nfn parse() {}\n").unwrap();
+        let impls = index_items("impl Point {\n    fn new() -> Point { Point }\n}\nfn parse() {}\n").unwrap();
+        let pages = types.iter().map(|item| (item, Some(3)))
+            .chain(impls.iter().map(|item| (item, Some(7))));
+        let targets = link_targets(pages);
+        assert_eq!(targets.get("Point"), Some(&3));
+        assert_eq!(targets.get("Point::new"), Some(&7));
+        // Defined in both files
+        assert_eq!(targets.get("parse"), None);
+    }
+}
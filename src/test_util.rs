@@ -0,0 +1,11 @@
+//! Helpers for tests that need a directory tree on disk
+
+use std::fs;
+use std::path::Path;
+
+/// Write `content` to `path` below `root`, creating the parent directories
+pub fn write_file(root: &Path, path: impl AsRef<Path>, content: &str) {
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}